@@ -1,18 +1,22 @@
 use smile_marco::Builder;
 
 #[derive(Builder)]
+#[builder(into, strip_option)]
 struct Book {
     title: String,
     price: i32,
     author: String,
+    subtitle: Option<String>,
 }
 
 fn main() {
     let book = Book::builder()
-        .title("Rust Programming".to_string())
+        .title("Rust Programming")
         .price(100)
-        .author("rust".to_string())
+        .author("rust")
         .build();
     assert_eq!(book.title, "Rust Programming".to_string());
     assert_eq!(book.price, 100);
+    assert_eq!(book.author, "rust".to_string());
+    assert_eq!(book.subtitle, None);
 }
@@ -0,0 +1,32 @@
+use smile_marco::{Builder, Getter, Setter, Wither};
+use std::fmt::Debug;
+
+#[derive(Builder, Getter, Setter, Wither)]
+struct Page<'a, T: Clone + Debug = u8, const N: usize = 2>
+where
+    T: Default,
+{
+    title: &'a str,
+    item: T,
+    lines: [u8; N],
+}
+
+fn main() {
+    let page: Page = Page::builder()
+        .title("Rust Programming")
+        .item(1)
+        .lines([1, 2])
+        .build();
+    let mut page = page.with_item(|v| v + 1);
+    page.set_title("Rust Book");
+    assert_eq!(page.get_title(), &"Rust Book");
+    assert_eq!(page.get_item(), &2);
+    assert_eq!(page.get_lines(), &[1, 2]);
+
+    let page = Page::<String, 3>::builder()
+        .title("Rust Programming")
+        .item("rust".to_string())
+        .lines([1, 2, 3])
+        .build();
+    assert_eq!(page.get_item(), "rust");
+}
@@ -1,3 +1,6 @@
+// the bare `#[name]` and `#[exclude]` forms are deprecated but still supported
+#![allow(deprecated)]
+
 use smile_marco::Getter;
 
 #[derive(Getter)]
@@ -17,4 +20,5 @@ fn main() {
     };
     assert_eq!(book.get_title(), &"Rust Programming".to_string());
     assert_eq!(book.get_cost(), &100);
+    assert_eq!(book.author, "rust".to_string());
 }
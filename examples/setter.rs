@@ -3,9 +3,9 @@ use smile_marco::Setter;
 #[derive(Setter)]
 struct Book {
     title: String,
-    #[name(cost)] // rename
+    #[smile(name = "cost")] // rename
     price: i32,
-    #[exclude] // Do not generate set methods for author
+    #[smile(skip)] // Do not generate set methods for author
     author: String,
 }
 
@@ -18,4 +18,6 @@ fn main() {
 
     book.set_cost(200);
     assert_eq!(book.price, 200);
+    assert_eq!(book.title, "Rust Programming".to_string());
+    assert_eq!(book.author, "rust".to_string());
 }
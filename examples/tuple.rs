@@ -0,0 +1,16 @@
+use smile_marco::{Builder, Getter, Setter, Wither};
+
+#[derive(Builder, Getter, Setter, Wither)]
+struct Point(f64, #[smile(name = "y")] f64);
+
+fn main() {
+    // positional fields are named after their index unless renamed
+    let mut point = Point::builder()._0(1.0).y(2.0).build();
+    assert_eq!(point.get_0(), &1.0);
+    assert_eq!(point.get_y(), &2.0);
+
+    point.set_0(3.0);
+    let point = point.with_y(|y| y * 2.0);
+    assert_eq!(point.0, 3.0);
+    assert_eq!(point.1, 4.0);
+}
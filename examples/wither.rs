@@ -3,14 +3,14 @@ use smile_marco::Wither;
 #[derive(Wither)]
 struct Book {
     title: String,
-    #[name(cost)] // rename
+    #[smile(name = "cost")] // rename
     price: i32,
-    #[exclude] // Do not generate set methods for author
+    #[smile(skip)] // Do not generate set methods for author
     author: String,
 }
 
 fn main() {
-    let mut book = Book {
+    let book = Book {
         title: "Rust Programming".to_string(),
         price: 100,
         author: "rust".to_string(),
@@ -18,4 +18,6 @@ fn main() {
 
     let book = book.with_cost(|v| v + 100);
     assert_eq!(book.price, 200);
+    assert_eq!(book.title, "Rust Programming".to_string());
+    assert_eq!(book.author, "rust".to_string());
 }
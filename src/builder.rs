@@ -1,42 +1,329 @@
-use crate::util::StructContext;
-use crate::TokenStream1;
-use quote::{format_ident, quote};
-use syn::DeriveInput;
+use crate::util::{
+    collect_results, deprecated_attrs, field_member, parse_flag, type_args, Collection,
+    DeriveOptions, FieldAttrs, MacroKind, MethodVis, StructContext,
+};
+use crate::{TokenStream1, TokenStream2};
+use quote::{format_ident, quote, ToTokens};
+use syn::meta::ParseNestedMeta;
+use syn::spanned::Spanned;
+use syn::{
+    DeriveInput, Field, GenericParam, Generics, Ident, LitStr, Member, Path, Token, Type, TypeParam,
+};
+
+/// Options declared through `#[builder(...)]`, fields inherit the ones set on the struct
+#[derive(Default, Clone)]
+struct BuilderOptions {
+    // track required fields in the builder's type parameters, struct level only
+    typestate: bool,
+    // expression used when the field is never set, `None` for required fields
+    default: Option<TokenStream2>,
+    // visibility of the builder type on the struct, of the setter on a field
+    vis: MethodVis,
+    // setters take `impl Into<T>` instead of `T`
+    into: bool,
+    // setters of `Option<T>` fields take `T`, the field is `None` unless set
+    strip_option: bool,
+    // name of the method adding one item to a collection field, field level only
+    each: Option<Ident>,
+}
+
+impl DeriveOptions for BuilderOptions {
+    fn parse_option(&mut self, meta: &ParseNestedMeta, on_field: bool) -> syn::Result<()> {
+        if meta.path.is_ident("typestate") {
+            if on_field {
+                return Err(meta.error("`typestate` can only be set on the struct"));
+            }
+            self.typestate = true;
+            Ok(())
+        } else if self.vis.parse_option(meta)? {
+            Ok(())
+        } else if meta.path.is_ident("into") {
+            self.into = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("strip_option") {
+            self.strip_option = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("each") {
+            if !on_field {
+                return Err(meta.error("`each` can only be set on fields"));
+            }
+            self.each = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("required") {
+            self.default = None;
+            Ok(())
+        } else if meta.path.is_ident("default") {
+            self.default = Some(if meta.input.peek(Token![=]) {
+                let expr: LitStr = meta.value()?.parse()?;
+                expr.parse::<syn::Expr>()?.into_token_stream()
+            } else {
+                quote!(::core::default::Default::default())
+            });
+            Ok(())
+        } else if meta.path.is_ident("default_fn") {
+            let path: Path = meta.value()?.parse()?;
+            self.default = Some(quote!(#path()));
+            Ok(())
+        } else {
+            Err(meta.error(
+                "unsupported builder attribute, expected `typestate`, `default`, `default_fn`, `required`, `vis`, `into`, `strip_option` or `each`",
+            ))
+        }
+    }
+}
+
+/// A field of the source struct as seen by the builder
+struct BuilderField<'a> {
+    // how the source struct refers to the field
+    member: Member,
+    // name of the field inside the builder
+    slot: Ident,
+    ty: &'a Type,
+    // name of the setter method, taken from `#[smile(name = "...")]` if present
+    method: Ident,
+    // excluded fields have no setter and are always filled from their default
+    exclude: bool,
+    // expression used when the field is never set, `None` for required fields
+    default: Option<TokenStream2>,
+    // visibility of the setter method
+    vis: TokenStream2,
+    // type taken by the setter, and the expression turning its `v` parameter into the field
+    param_ty: TokenStream2,
+    param_value: TokenStream2,
+    // method adding one item to the collection, from `#[builder(each = "...")]`
+    each: Option<(Ident, Collection<'a>)>,
+}
+
+impl<'a> BuilderField<'a> {
+    fn new(index: usize, field: &'a Field, attrs: FieldAttrs<BuilderOptions>) -> syn::Result<Self> {
+        let mut default = attrs.options.default;
+        let member = field_member(field, index);
+        let slot = match field.ident {
+            Some(ref ident) => ident.clone(),
+            None => format_ident!("_{}", index),
+        };
+        if attrs.exclude && default.is_none() {
+            default = Some(quote!(::core::default::Default::default()));
+        }
+        // `strip_option` only affects `Option<T>` fields, so it can be set on the struct
+        let (inner_ty, wrap) = match type_args(&field.ty, "Option").as_deref() {
+            Some([inner]) if attrs.options.strip_option => {
+                default.get_or_insert_with(|| quote!(::core::option::Option::None));
+                (quote!(#inner), true)
+            }
+            _ => (field.ty.to_token_stream(), false),
+        };
+        let (param_ty, mut param_value) = if attrs.options.into {
+            (
+                quote!(impl ::core::convert::Into<#inner_ty>),
+                quote!(::core::convert::Into::into(v)),
+            )
+        } else {
+            (inner_ty, quote!(v))
+        };
+        if wrap {
+            param_value = quote!(::core::option::Option::Some(#param_value));
+        }
+        // positional fields get setters such as `_0` unless they are renamed
+        let mut method = syn::parse_str::<Ident>(&attrs.name)
+            .unwrap_or_else(|_| format_ident!("_{}", attrs.name));
+        method.set_span(member.span());
+        let each = match attrs.options.each {
+            Some(each) => {
+                let collection = Collection::of(&field.ty).ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        &field.ty,
+                        "`each` expects a Vec, VecDeque, HashSet, BTreeSet, HashMap or BTreeMap field",
+                    )
+                })?;
+                // collections filled item by item start empty
+                default.get_or_insert_with(|| quote!(::core::default::Default::default()));
+                Some((each, collection))
+            }
+            None => None,
+        };
+        Ok(Self {
+            member,
+            slot,
+            ty: &field.ty,
+            method,
+            exclude: attrs.exclude,
+            default,
+            vis: attrs.options.vis.resolve(quote!(pub), &field.vis),
+            param_ty,
+            param_value,
+            each,
+        })
+    }
+
+    /// Whether the whole-value setter is generated, it is replaced by the item setter when
+    /// both have the same name
+    fn has_setter(&self) -> bool {
+        !self.exclude
+            && self
+                .each
+                .as_ref()
+                .is_none_or(|(each, _)| *each != self.method)
+    }
+
+    /// Method adding one item to the collection, creating it on first use
+    fn each_setter(&self) -> Option<TokenStream2> {
+        let (each, collection) = self.each.as_ref().filter(|_| !self.exclude)?;
+        let ident = &self.slot;
+        let vis = &self.vis;
+        let collection_value =
+            quote!(self.#ident.get_or_insert_with(::core::default::Default::default));
+        let (params, add) = match collection {
+            Collection::List { item, deque: false } => {
+                (quote!(item: #item), quote!(#collection_value.push(item)))
+            }
+            Collection::List { item, deque: true } => (
+                quote!(item: #item),
+                quote!(#collection_value.push_back(item)),
+            ),
+            Collection::Set { item } => {
+                (quote!(item: #item), quote!(#collection_value.insert(item);))
+            }
+            Collection::Map { key, value } => (
+                quote!(key: #key, value: #value),
+                quote!(#collection_value.insert(key, value);),
+            ),
+        };
+        Some(quote! {
+            #[inline]
+            #vis fn #each (mut self, #params) -> Self {
+                #add;
+                self
+            }
+        })
+    }
+
+    /// Expression producing the field value in `build`, where `required` takes the value out
+    /// of the builder slot of a field without a default
+    fn build_value(&self, required: TokenStream2) -> TokenStream2 {
+        let ident = &self.slot;
+        match &self.default {
+            Some(default) if self.exclude => quote!(#default),
+            Some(default) => quote!(self.#ident.unwrap_or_else(|| #default)),
+            None => required,
+        }
+    }
+}
 
 pub(crate) fn gen_ast(input: &DeriveInput) -> TokenStream1 {
-    let struct_name = format_ident!("{}Builder", &input.ident);
-    let source_struct_name = &input.ident;
+    let mut ast = expand(input).unwrap_or_else(syn::Error::into_compile_error);
+    ast.extend(deprecated_attrs(input));
+    ast.into()
+}
 
-    let context = StructContext::new(input);
-    let vis = context.vis;
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let context = StructContext::new(input)?;
+    let (options, attrs) = context.parse_fields_attrs::<BuilderOptions>(MacroKind::Builder)?;
+    let fields = context
+        .fields
+        .iter()
+        .zip(attrs)
+        .enumerate()
+        .map(|(i, (f, attrs))| BuilderField::new(i, f, attrs));
+    let fields = collect_results(fields)?;
+    // the builder type follows the struct unless `vis` is set on the struct
+    let vis = options
+        .vis
+        .resolve(context.vis.to_token_stream(), context.vis);
+    let ast = if options.typestate {
+        gen_typestate_ast(&context, &vis, &fields)
+    } else {
+        gen_option_ast(&context, &vis, &fields)
+    };
+    Ok(ast)
+}
+
+/// Generates a builder that stores every field as `Option` and checks them in `build`
+/// or `try_build`
+fn gen_option_ast(
+    context: &StructContext,
+    vis: &TokenStream2,
+    fields: &[BuilderField],
+) -> TokenStream2 {
+    let source_struct_name = context.ident;
+    let struct_name = format_ident!("{}Builder", source_struct_name);
+    let error_name = format_ident!("{}BuilderError", source_struct_name);
     let generics = context.generics;
     let where_case = context.where_case;
     let impl_sign = context.impl_sign();
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
 
-    let fields = context.fields;
-
-    let field_idents = fields.iter().map(|f| f.ident.as_ref()).collect::<Vec<_>>();
-    let field_tys = fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
-    let field_expects = fields
+    let all_members = fields.iter().map(|f| &f.member).collect::<Vec<_>>();
+    let field_values = fields
         .iter()
         .map(|f| {
-            format!(
+            let ident = &f.slot;
+            let expect = format!(
                 "{} field is not set in {} struct",
-                f.ident.as_ref().unwrap(),
-                source_struct_name
-            )
+                f.method, source_struct_name
+            );
+            f.build_value(quote!(self.#ident.expect(#expect)))
         })
         .collect::<Vec<_>>();
+    let settable = fields.iter().filter(|f| !f.exclude);
+    let field_idents = settable.clone().map(|f| &f.slot).collect::<Vec<_>>();
+    let marker = marker_ident(fields);
+    let field_tys = settable.clone().map(|f| f.ty).collect::<Vec<_>>();
+    let with_setter = fields.iter().filter(|f| f.has_setter());
+    let setter_idents = with_setter.clone().map(|f| &f.slot);
+    let setter_methods = with_setter.clone().map(|f| &f.method);
+    let setter_vis = with_setter.clone().map(|f| &f.vis);
+    let param_tys = with_setter.clone().map(|f| &f.param_ty);
+    let param_values = with_setter.map(|f| &f.param_value);
+    let each_setters = fields.iter().filter_map(BuilderField::each_setter);
+    // only fields without a default can be missing
+    let required = settable.filter(|f| f.default.is_none());
+    let required_idents = required.clone().map(|f| &f.slot).collect::<Vec<_>>();
+    let required_names = required.map(|f| f.method.to_string()).collect::<Vec<_>>();
+    let required_count = required_idents.len();
+    let error_display = format!("missing fields in {} builder: ", source_struct_name);
 
-    let ast = quote! {
+    quote! {
         #vis struct #struct_name #generics #where_case {
             #(
                 #field_idents: Option<#field_tys>,
             )*
+            #marker: ::core::marker::PhantomData<fn() -> #source_struct_name #ty_generics>,
+        }
+
+        /// Error returned by `try_build` when some fields were never set
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #vis struct #error_name {
+            // the first `count` entries name the fields that were not set, in declaration order
+            fields: [&'static str; #required_count],
+            count: usize,
+        }
+
+        impl #error_name {
+            /// Names of every field that was not set, in declaration order
+            #[inline]
+            pub fn missing_fields(&self) -> &[&'static str] {
+                self.fields.get(..self.count).unwrap_or(&self.fields)
+            }
+        }
+
+        impl ::core::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(#error_display)?;
+                for (index, field) in self.missing_fields().iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    f.write_str(field)?;
+                }
+                ::core::result::Result::Ok(())
+            }
         }
 
+        impl ::core::error::Error for #error_name {}
+
 
-        impl #generics #struct_name #generics {
+        impl #impl_generics #struct_name #ty_generics #where_case {
 
             #[inline]
             pub fn new() -> Self {
@@ -44,33 +331,274 @@ pub(crate) fn gen_ast(input: &DeriveInput) -> TokenStream1 {
                     #(
                         #field_idents: None,
                     )*
+                    #marker: ::core::marker::PhantomData,
                 }
             }
 
             #[inline]
-            pub fn build(self) -> #source_struct_name #generics{
+            pub fn build(self) -> #source_struct_name #ty_generics {
                 #source_struct_name {
                     #(
-                        #field_idents: self.#field_idents.expect(#field_expects),
+                        #all_members: #field_values,
                     )*
                 }
             }
+
+            #[inline]
+            pub fn try_build(self) -> ::core::result::Result<#source_struct_name #ty_generics, #error_name> {
+                #[allow(unused_mut)]
+                let mut fields = [""; #required_count];
+                #[allow(unused_mut)]
+                let mut count = 0;
+                #(
+                    if self.#required_idents.is_none() {
+                        fields[count] = #required_names;
+                        count += 1;
+                    }
+                )*
+                if count > 0 {
+                    return ::core::result::Result::Err(#error_name { fields, count });
+                }
+                ::core::result::Result::Ok(self.build())
+            }
             #(
                 #[inline]
-                pub fn #field_idents (mut self,v:#field_tys) -> Self {
-                    self.#field_idents = Some(v);
+                #setter_vis fn #setter_methods (mut self,v:#param_tys) -> Self {
+                    self.#setter_idents = Some(#param_values);
                     self
                 }
             )*
+            #(#each_setters)*
 
         }
 
        #impl_sign {
             #[inline]
-            pub fn builder() -> #struct_name #generics{
+            #vis fn builder() -> #struct_name #ty_generics {
                 #struct_name::new()
             }
         }
-    };
-    ast.into()
+    }
+}
+
+/// Generates a builder whose type parameters record which required fields have been set.
+///
+/// Every required field gets its own state parameter, which is `()` while the field is unset
+/// and `(T,)` once its setter has been called, so `build` only exists when all of them are
+/// `(T,)`. Fields with a default are stored as `Option` and never block `build`.
+fn gen_typestate_ast(
+    context: &StructContext,
+    vis: &TokenStream2,
+    all_fields: &[BuilderField],
+) -> TokenStream2 {
+    let source_struct_name = context.ident;
+    let struct_name = format_ident!("{}Builder", source_struct_name);
+    let (impl_generics, ty_generics, where_case) = context.generics.split_for_impl();
+
+    let all_members = all_fields.iter().map(|f| &f.member).collect::<Vec<_>>();
+    let fields = all_fields.iter().filter(|f| !f.exclude).collect::<Vec<_>>();
+    let field_idents = fields.iter().map(|f| &f.slot).collect::<Vec<_>>();
+    let marker = marker_ident(all_fields);
+    // state parameter of each required field, named by position so that raw or similar
+    // field names cannot produce invalid or clashing parameters
+    let states = fields
+        .iter()
+        .enumerate()
+        .map(|(index, f)| {
+            f.default
+                .is_none()
+                .then(|| format_ident!("__State{}", index))
+        })
+        .collect::<Vec<_>>();
+    let state_params = states.iter().flatten().collect::<Vec<_>>();
+
+    // the builder carries the struct generics plus one parameter per required field state
+    let mut struct_generics = context.generics.clone();
+    for param in struct_generics.params.iter_mut() {
+        if let GenericParam::Type(ty) = param {
+            ty.eq_token = None;
+            ty.default = None;
+        } else if let GenericParam::Const(c) = param {
+            c.eq_token = None;
+            c.default = None;
+        }
+    }
+    let builder_generics = with_states(&struct_generics, state_params.iter().copied());
+    let (builder_impl_generics, _, _) = builder_generics.split_for_impl();
+    let (struct_impl_generics, _, _) = struct_generics.split_for_impl();
+    let struct_args = struct_generics
+        .params
+        .iter()
+        .map(generic_arg)
+        .collect::<Vec<_>>();
+
+    let slot_tys = fields.iter().zip(&states).map(|(f, state)| {
+        let ty = f.ty;
+        match state {
+            Some(state) => quote!(#state),
+            None => quote!(Option<#ty>),
+        }
+    });
+    let slot_inits = states.iter().map(|state| match state {
+        Some(_) => quote!(()),
+        None => quote!(None),
+    });
+    let unset = state_params.iter().map(|_| quote!(())).collect::<Vec<_>>();
+    let set = fields
+        .iter()
+        .filter(|f| f.default.is_none())
+        .map(|f| {
+            let ty = f.ty;
+            quote!((#ty,))
+        })
+        .collect::<Vec<_>>();
+    let build_values = all_fields.iter().map(|f| {
+        let ident = &f.slot;
+        f.build_value(quote!(self.#ident.0))
+    });
+
+    let setters = fields.iter().enumerate().map(|(index, field)| {
+        let ident = &field.slot;
+        let method = &field.method;
+        let method_vis = &field.vis;
+        let ty = field.ty;
+        let (param_ty, value) = (&field.param_ty, &field.param_value);
+        let Some(current) = &states[index] else {
+            // optional fields can be set in any state
+            let setter = field.has_setter().then(|| {
+                quote! {
+                    #[inline]
+                    #method_vis fn #method (mut self, v: #param_ty) -> Self {
+                        self.#ident = Some(#value);
+                        self
+                    }
+                }
+            });
+            let each_setter = field.each_setter();
+            return quote! {
+                impl #builder_impl_generics #struct_name <#(#struct_args,)* #(#state_params),*> #where_case {
+                    #setter
+                    #each_setter
+                }
+            };
+        };
+        // every state except the current one stays generic in the setter impl
+        let setter_generics = with_states(
+            &struct_generics,
+            state_params.iter().copied().filter(|state| *state != current),
+        );
+        let (setter_impl_generics, _, _) = setter_generics.split_for_impl();
+        let before = state_params.iter().map(|state| {
+            if *state == current {
+                quote!(())
+            } else {
+                quote!(#state)
+            }
+        });
+        let after = state_params.iter().map(|state| {
+            if *state == current {
+                quote!((#ty,))
+            } else {
+                quote!(#state)
+            }
+        });
+        let values = field_idents.iter().map(|other| {
+            if *other == ident {
+                quote!(#other: (#value,))
+            } else {
+                quote!(#other: self.#other)
+            }
+        });
+        quote! {
+            impl #setter_impl_generics #struct_name <#(#struct_args,)* #(#before),*> #where_case {
+                #[inline]
+                #method_vis fn #method (self, v: #param_ty) -> #struct_name <#(#struct_args,)* #(#after),*> {
+                    #struct_name {
+                        #(#values,)*
+                        #marker: ::core::marker::PhantomData,
+                    }
+                }
+            }
+        }
+    });
+
+    quote! {
+        #vis struct #struct_name #builder_impl_generics #where_case {
+            #(
+                #field_idents: #slot_tys,
+            )*
+            #marker: ::core::marker::PhantomData<fn() -> #source_struct_name #ty_generics>,
+        }
+
+        impl #struct_impl_generics #struct_name <#(#struct_args,)* #(#unset),*> #where_case {
+            #[inline]
+            pub fn new() -> Self {
+                Self {
+                    #(
+                        #field_idents: #slot_inits,
+                    )*
+                    #marker: ::core::marker::PhantomData,
+                }
+            }
+        }
+
+        impl #struct_impl_generics #struct_name <#(#struct_args,)* #(#set),*> #where_case {
+            #[inline]
+            pub fn build(self) -> #source_struct_name #ty_generics {
+                #source_struct_name {
+                    #(
+                        #all_members: #build_values,
+                    )*
+                }
+            }
+        }
+
+        #(#setters)*
+
+        impl #impl_generics #source_struct_name #ty_generics #where_case {
+            #[inline]
+            #vis fn builder() -> #struct_name <#(#struct_args,)* #(#unset),*> {
+                #struct_name::new()
+            }
+        }
+    }
+}
+
+/// Appends the given state parameters to a copy of the struct generics
+fn with_states<'a>(generics: &Generics, states: impl Iterator<Item = &'a Ident>) -> Generics {
+    let mut generics = generics.clone();
+    for state in states {
+        generics
+            .params
+            .push(GenericParam::Type(TypeParam::from(state.clone())));
+    }
+    generics
+}
+
+/// Name of the builder slot holding the `PhantomData`, `__marker` unless a field already
+/// uses it, in which case underscores are appended until the name is free
+fn marker_ident(fields: &[BuilderField]) -> Ident {
+    let mut marker = String::from("__marker");
+    while fields.iter().any(|f| f.slot == marker) {
+        marker.push('_');
+    }
+    Ident::new(&marker, proc_macro2::Span::call_site())
+}
+
+/// Turns a generic parameter declaration into the argument that refers to it
+fn generic_arg(param: &GenericParam) -> TokenStream2 {
+    match param {
+        GenericParam::Type(ty) => {
+            let ident = &ty.ident;
+            quote!(#ident)
+        }
+        GenericParam::Lifetime(lt) => {
+            let lifetime = &lt.lifetime;
+            quote!(#lifetime)
+        }
+        GenericParam::Const(c) => {
+            let ident = &c.ident;
+            quote!(#ident)
+        }
+    }
 }
@@ -1,65 +1,103 @@
-use crate::util::token_tree_utils;
 use crate::{TokenStream1, TokenStream2};
-use proc_macro2::TokenTree;
-use quote::{format_ident, quote};
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{bracketed, DeriveInput, Ident, LitStr, Path, Token};
+
+const DERIVES: [&str; 4] = ["Getter", "Setter", "Wither", "Builder"];
 
 pub(crate) fn ast_gen(attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
-    if !token_tree_utils::is_struct(&item) {
-        return item;
+    // `pub struct` and attributes before `struct` are allowed, other items are left untouched
+    match syn::parse::<DeriveInput>(item.clone()) {
+        Ok(DeriveInput {
+            data: syn::Data::Struct(_),
+            ..
+        }) => {}
+        _ => return item,
     }
-    let mut link = format_ident!("smile_marco");
-    let mut need_derives = vec![
-        "Getter".to_string(),
-        "Setter".to_string(),
-        "Builder".to_string(),
-        "Wither".to_string(),
-    ];
-    let attr = TokenStream2::from(attr);
-    let mut attr_iter = attr.into_iter();
-    while let Some(TokenTree::Ident(ref ident)) = attr_iter.next() {
-        if ident.to_string().eq("link")
-            && token_tree_utils::punct_eq(attr_iter.next().as_ref(), "=")
-        {
-            if let Some(TokenTree::Literal(ref lit)) = attr_iter.next() {
-                let v = lit.to_string();
-                let v = v.replace("\"", "");
-                link = format_ident!("{}", v);
-            }
-        } else if ident.to_string().eq("exclude")
-            && token_tree_utils::punct_eq(attr_iter.next().as_ref(), "=")
-        {
-            if let Some(TokenTree::Group(ref group)) = attr_iter.next() {
-                let exclude = group
-                    .stream()
-                    .into_iter()
-                    .filter(|v| matches!(v, TokenTree::Literal(_) | TokenTree::Ident(_)))
-                    .map(|v| {
-                        let str = v.to_string();
-                        str.replace("\"", "")
-                    })
-                    .collect::<Vec<_>>();
-                let vec = need_derives
-                    .iter()
-                    .filter(|v| !exclude.contains(v))
-                    .map(String::from)
-                    .collect::<Vec<_>>();
-                need_derives = vec;
-            }
+    let mut link: Path = syn::parse_quote!(smile_marco);
+    let mut include: Option<Vec<String>> = None;
+    let mut exclude: Vec<String> = Vec::new();
+    let mut extra_derives: Vec<Path> = Vec::new();
+
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("link") {
+            let lit: LitStr = meta.value()?.parse()?;
+            link = lit.parse()?;
+            Ok(())
+        } else if meta.path.is_ident("include") {
+            include = Some(parse_derive_names(&meta)?);
+            Ok(())
+        } else if meta.path.is_ident("exclude") {
+            exclude.extend(parse_derive_names(&meta)?);
+            Ok(())
+        } else if meta.path.is_ident("derive") {
+            let value = meta.value()?;
+            let content;
+            bracketed!(content in value);
+            extra_derives.extend(Punctuated::<Path, Token![,]>::parse_terminated(&content)?);
+            Ok(())
+        } else {
+            Err(meta.error(
+                "unsupported data attribute, expected `link`, `include`, `exclude` or `derive`",
+            ))
         }
+    });
+    if let Err(err) = parser.parse(attr) {
+        let source_stream = TokenStream2::from(item);
+        let err = err.into_compile_error();
+        return quote!(#err #source_stream).into();
     }
 
-    let need_derives = need_derives
+    let need_derives = DERIVES
         .iter()
-        .map(|v| format_ident!("{}", v))
+        .filter(|v| {
+            include
+                .as_ref()
+                .is_none_or(|include| include.iter().any(|i| i == *v))
+        })
+        .filter(|v| !exclude.iter().any(|e| e == *v))
+        .map(|v| Ident::new(v, proc_macro2::Span::call_site()))
         .collect::<Vec<_>>();
 
     let source_stream = TokenStream2::from(item);
     let ast = quote! {
-        use #link::{
-            #(#need_derives ,)*
-        };
-        #[derive(Getter, Setter, Wither, Builder)]
+        #[derive(#(#link::#need_derives,)* #(#extra_derives,)*)]
         #source_stream
     };
     ast.into()
 }
+
+/// Parses a list such as `["Getter", "Setter"]` or `[Getter, Setter]`, checking that every
+/// entry names one of the derives added by `data`
+fn parse_derive_names(meta: &ParseNestedMeta) -> syn::Result<Vec<String>> {
+    let value = meta.value()?;
+    let content;
+    bracketed!(content in value);
+    let mut names = Vec::new();
+    while !content.is_empty() {
+        let (name, span) = if content.peek(LitStr) {
+            let lit: LitStr = content.parse()?;
+            (lit.value(), lit.span())
+        } else {
+            let ident: Ident = content.parse()?;
+            (ident.to_string(), ident.span())
+        };
+        if !DERIVES.contains(&name.as_str()) {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "unknown derive `{}`, expected one of {}",
+                    name,
+                    DERIVES.join(", ")
+                ),
+            ));
+        }
+        names.push(name);
+        if !content.is_empty() {
+            content.parse::<Token![,]>()?;
+        }
+    }
+    Ok(names)
+}
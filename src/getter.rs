@@ -1,28 +1,143 @@
-use crate::util::StructContext;
-use crate::TokenStream1;
+use crate::util::{
+    deprecated_attrs, is_copy_primitive, parse_flag, type_args, DeriveOptions, EnumContext,
+    MacroKind, MethodName, MethodVis, StructContext, VariantFieldContext,
+};
+use crate::{TokenStream1, TokenStream2};
 use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
 use syn::spanned::Spanned;
-use syn::DeriveInput;
+use syn::{DeriveInput, Ident, Type};
+
+/// Options declared through `#[getter(...)]`, fields inherit the ones set on the struct
+#[derive(Default, Clone)]
+struct GetterOptions {
+    method: MethodName,
+    vis: MethodVis,
+    // `copy` or `copy = false`, `None` leaves the choice to `copy_primitives`
+    copy: Option<bool>,
+    // return primitive types such as `i32` by value
+    copy_primitives: bool,
+    // return the borrowed view of well-known std types, such as `&str` for `String`
+    deref: bool,
+    // also generate `get_x_mut` returning `&mut T`
+    mutable: bool,
+}
+
+impl GetterOptions {
+    /// Whether the getter of a field of type `ty` returns a copy instead of a reference
+    fn by_value(&self, ty: &Type) -> bool {
+        self.copy
+            .unwrap_or_else(|| self.copy_primitives && is_copy_primitive(ty))
+    }
+}
+
+/// Return type and expression of a getter reading the field at `place`
+fn getter_output(
+    options: &GetterOptions,
+    ty: &Type,
+    place: TokenStream2,
+) -> (TokenStream2, TokenStream2) {
+    if options.by_value(ty) {
+        return (quote!(#ty), place);
+    }
+    let value = quote!(&#place);
+    if options.deref {
+        if let Some(view) = deref_view(ty, &value) {
+            return view;
+        }
+    }
+    (quote!(&#ty), value)
+}
+
+/// Borrowed view of `String`, `Vec`, `Box`, `Rc`, `Arc`, `PathBuf` and `Option` fields,
+/// `None` for other types
+fn deref_view(ty: &Type, value: &TokenStream2) -> Option<(TokenStream2, TokenStream2)> {
+    let deref = quote!(::core::ops::Deref::deref(#value));
+    if type_args(ty, "String").is_some() {
+        Some((quote!(&str), deref))
+    } else if type_args(ty, "PathBuf").is_some() {
+        Some((quote!(&::std::path::Path), deref))
+    } else if let Some([inner]) = type_args(ty, "Vec").as_deref() {
+        Some((quote!(&[#inner]), deref))
+    } else if let Some([inner]) = ["Box", "Rc", "Arc"]
+        .iter()
+        .find_map(|name| type_args(ty, name))
+        .as_deref()
+    {
+        Some((quote!(&#inner), deref))
+    } else if let Some([inner]) = type_args(ty, "Option").as_deref() {
+        Some((
+            quote!(::core::option::Option<&#inner>),
+            quote!(::core::option::Option::as_ref(#value)),
+        ))
+    } else {
+        None
+    }
+}
+
+impl DeriveOptions for GetterOptions {
+    fn parse_option(&mut self, meta: &ParseNestedMeta, _: bool) -> syn::Result<()> {
+        if self.method.parse_option(meta)? || self.vis.parse_option(meta)? {
+            Ok(())
+        } else if meta.path.is_ident("copy") {
+            self.copy = Some(parse_flag(meta)?);
+            Ok(())
+        } else if meta.path.is_ident("copy_primitives") {
+            self.copy_primitives = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("deref") {
+            self.deref = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("mut") {
+            self.mutable = parse_flag(meta)?;
+            Ok(())
+        } else {
+            Err(meta.error(
+                "unsupported getter attribute, expected `prefix`, `suffix`, `vis`, `copy`, `copy_primitives`, `deref` or `mut`",
+            ))
+        }
+    }
+}
 
 pub(crate) fn gen_ast(input: &DeriveInput) -> TokenStream1 {
-    let context = StructContext::new(input);
+    let mut ast = expand(input).unwrap_or_else(syn::Error::into_compile_error);
+    ast.extend(deprecated_attrs(input));
+    ast.into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    if let syn::Data::Enum(_) = input.data {
+        return expand_enum(input);
+    }
+    let context = StructContext::new(input)?;
     let impl_sign = context.impl_sign();
-    let fields = context.handle_fields_exclude_and_name_attr();
+    let fields = context.handle_fields_exclude_and_name_attr::<GetterOptions>(MacroKind::Getter)?;
 
     let gen_fns = fields
         .iter()
         .map(|fc| {
-            let mut fn_name = format_ident!("get_{}", fc.name);
-            fn_name.set_span(fc.ident.span());
-
-            let ty = fc.ty;
-            let ident = fc.ident;
-            quote! {
-                 #[inline]
-                pub fn #fn_name (&self)-> &#ty {
-                    &self.#ident
+            let fn_name = fc.options.method.ident("get_", &fc.name, fc.member.span());
+            let vis = fc.options.vis.resolve(quote!(pub), fc.vis);
+
+            let ident = &fc.member;
+            let (ty, value) = getter_output(&fc.options, fc.ty, quote!(self.#ident));
+            let mut getter = quote! {
+                #[inline]
+                #vis fn #fn_name (&self)-> #ty {
+                    #value
                 }
+            };
+            if fc.options.mutable {
+                let mut_name = format_ident!("{}_mut", fn_name, span = fn_name.span());
+                let ty = fc.ty;
+                getter.extend(quote! {
+                    #[inline]
+                    #vis fn #mut_name (&mut self)-> &mut #ty {
+                        &mut self.#ident
+                    }
+                });
             }
+            getter
         })
         .collect::<Vec<_>>();
 
@@ -31,5 +146,79 @@ pub(crate) fn gen_ast(input: &DeriveInput) -> TokenStream1 {
             #(#gen_fns)*
         }
     };
-    ast.into()
+    Ok(ast)
+}
+
+/// Enums get a getter for every named field: fields held by all variants return `&T`,
+/// the others return `Option<&T>` (`T` and `Option<T>` for copy getters). Mutable getters
+/// return `&mut T` or `Option<&mut T>` the same way
+fn expand_enum(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let context = EnumContext::new(input)?;
+    let impl_sign = context.impl_sign();
+    let fields =
+        context.handle_variant_fields_exclude_and_name_attr::<GetterOptions>(MacroKind::Getter)?;
+
+    let gen_fns = fields
+        .iter()
+        .map(|fc| {
+            let fn_name = fc.options.method.ident("get_", &fc.name, fc.span);
+            let vis = fc.options.vis.resolve(quote!(pub), context.vis);
+
+            let (ty, value) = getter_output(&fc.options, fc.ty, quote!((*value)));
+            let mut getter = enum_getter(fc, &vis, &fn_name, quote!(&self), ty, value);
+            if fc.options.mutable {
+                let mut_name = format_ident!("{}_mut", fn_name, span = fn_name.span());
+                let ty = fc.ty;
+                getter.extend(enum_getter(
+                    fc,
+                    &vis,
+                    &mut_name,
+                    quote!(&mut self),
+                    quote!(&mut #ty),
+                    quote!(value),
+                ));
+            }
+            getter
+        })
+        .collect::<Vec<_>>();
+
+    let ast = quote! {
+        #impl_sign {
+            #(#gen_fns)*
+        }
+    };
+    Ok(ast)
+}
+
+/// Getter matching every variant holding the field, where `value` is bound to the field
+fn enum_getter(
+    fc: &VariantFieldContext<GetterOptions>,
+    vis: &TokenStream2,
+    fn_name: &Ident,
+    receiver: TokenStream2,
+    ty: TokenStream2,
+    value: TokenStream2,
+) -> TokenStream2 {
+    let variants = fc.variants.iter().map(|(v, _)| v);
+    let members = fc.variants.iter().map(|(_, m)| m);
+    if fc.shared {
+        quote! {
+            #[inline]
+            #vis fn #fn_name (#receiver)-> #ty {
+                match self {
+                    #(Self::#variants { #members: value, .. } => #value,)*
+                }
+            }
+        }
+    } else {
+        quote! {
+            #[inline]
+            #vis fn #fn_name (#receiver)-> Option<#ty> {
+                match self {
+                    #(Self::#variants { #members: value, .. } => Some(#value),)*
+                    _ => None,
+                }
+            }
+        }
+    }
 }
@@ -19,17 +19,16 @@
 //! #[derive(Builder, Getter, Setter, Wither)]
 //! struct Book {
 //!     title: String,
-//!     #[name(cost)] // rename
+//!     #[smile(name = "cost")] // rename
 //!     price: i32,
-//!     #[exclude] // Do not generate set methods for author
+//!     #[smile(skip)] // Do not generate set methods or a builder setter for author
 //!     author: String,
 //! }
 //!
 //!
 //! let mut book = Book::builder()
 //! .title("Rust Programming".to_string())
-//! .price(100)
-//! .author("rust".to_string())
+//! .cost(100)
 //! .build();
 //! assert_eq!(book.title, "Rust Programming".to_string());
 //! book.set_title("Rust Book".into());
@@ -38,6 +37,102 @@
 //! let mut book = book.with_cost(|c| c + 100);
 //! assert_eq!(book.price, 200);
 //! ```
+//!
+//! # Helper attributes
+//! Every derive reads the namespaced `#[smile(...)]` attribute:
+//! - `#[smile(name = "cost")]` renames the generated methods of a field
+//! - `#[smile(skip)]` skips the field in every derive, `#[smile(skip(setter, wither))]` only in
+//!   the listed ones
+//! - `#[smile(builder(...))]` passes options to a single derive, `#[builder(...)]` is a shorter
+//!   form of it
+//!
+//! Derive options can also be written on the struct, where they apply to every field. A field
+//! starts from the struct options and only overrides the ones it sets itself.
+//!
+//! The bare `#[name(cost)]` and `#[exclude]` / `#[exclude(setter)]` attributes are deprecated
+//! aliases of `name` and `skip`, kept for compatibility. Using them raises a deprecation
+//! warning.
+//!
+//! ```compile_fail
+//! #![deny(deprecated)]
+//! use smile_marco::Getter;
+//! #[derive(Getter)]
+//! struct Book {
+//!     #[exclude]
+//!     id: u64,
+//! }
+//! ```
+//!
+//! They keep working, and `#[allow(deprecated)]` on the struct or the field silences the
+//! warning:
+//!
+//! ```
+//! #![deny(deprecated)]
+//! use smile_marco::{Getter, Setter};
+//! #[derive(Getter, Setter)]
+//! #[allow(deprecated)]
+//! struct Book {
+//!     #[name(cost)]
+//!     price: i32,
+//!     #[exclude(setter)]
+//!     id: u64,
+//!     #[exclude]
+//!     author: String,
+//! }
+//!
+//! let mut book = Book { price: 100, id: 1, author: "rust".to_string() };
+//! book.set_cost(200);
+//! assert_eq!(book.get_cost(), &200);
+//! assert_eq!(book.get_id(), &1);
+//! assert_eq!(book.author, "rust");
+//! ```
+//!
+//! Other shapes such as `#[name = "..."]` are left to the crates that own them.
+//!
+//! ```
+//! #![deny(deprecated)]
+//! use smile_marco::Getter;
+//! #[derive(Getter)]
+//! struct Book {
+//!     #[name = "book_id"]
+//!     id: u64,
+//! }
+//!
+//! assert_eq!(Book { id: 1 }.get_id(), &1);
+//! ```
+//!
+//! # Errors
+//!
+//! Misuse is reported as a compile error pointing at the offending code, and every bad
+//! attribute of a struct is reported at once rather than stopping at the first one.
+//!
+//! ```compile_fail
+//! use smile_marco::Setter;
+//! #[derive(Setter)] // setters are only generated for structs
+//! enum Shape {
+//!     Circle { radius: f64 },
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use smile_marco::Getter;
+//! #[derive(Getter)]
+//! struct Book {
+//!     #[name()] // the new name is missing
+//!     price: i32,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use smile_marco::Setter;
+//! #[derive(Setter)]
+//! struct Book {
+//!     #[setter(chained)] // both errors are reported
+//!     title: String,
+//!     #[setter(prefix = "set-")]
+//!     price: i32,
+//! }
+//! ```
 #[cfg(feature = "builder")]
 mod builder;
 #[cfg(feature = "full")]
@@ -60,9 +155,9 @@ type TokenStream2 = proc_macro2::TokenStream;
 /// #[derive(Getter)]
 /// struct Book {
 ///     title: String,
-///     #[name(cost)] //rename
+///     #[smile(name = "cost")] //rename
 ///     price: i32,
-///     #[exclude] // Do not generate get methods for author
+///     #[smile(skip)] // Do not generate get methods for author
 ///     author: String,
 /// }
 ///
@@ -74,9 +169,10 @@ type TokenStream2 = proc_macro2::TokenStream;
 ///  };
 ///  assert_eq!(book.get_title(), &"Rust Programming".to_string());
 ///  assert_eq!(book.get_cost(), &100);
+/// ```
 ///
-///
-/// //Getter Macros automatically generate the following code
+/// Getter Macros automatically generate the following code
+/// ```ignore
 /// impl Book {
 ///     #[inline]
 ///     pub fn get_title(&self) -> &String {
@@ -89,8 +185,130 @@ type TokenStream2 = proc_macro2::TokenStream;
 /// }
 /// ```
 ///
+/// # Enums
+///
+/// On an enum, a getter is generated for every named field. Fields held by every variant with
+/// the same type return `&T`, fields held by only some variants return `Option<&T>`.
+///
+/// ```
+/// use smile_marco::Getter;
+/// #[derive(Getter)]
+/// enum Event {
+///     Created { id: u64, name: String },
+///     Deleted { id: u64 },
+/// }
+///
+/// let event = Event::Deleted { id: 7 };
+/// assert_eq!(event.get_id(), &7);
+/// assert_eq!(event.get_name(), None);
+/// ```
+///
+/// # Method names
+///
+/// `prefix = "..."` replaces the `get_` prefix and `suffix = "..."` appends to the name. The
+/// same options exist for `Setter` and `Wither`. An empty prefix gives fluent accessors such as
+/// `book.title()`, where tuple fields are named `_0`, `_1`, ...
+///
+/// ```
+/// use smile_marco::Getter;
+/// #[derive(Getter)]
+/// #[getter(prefix = "")]
+/// struct Book {
+///     title: String,
+///     #[getter(prefix = "read_", suffix = "_raw")]
+///     price: i32,
+/// }
+///
+/// let book = Book { title: "Rust Programming".to_string(), price: 100 };
+/// assert_eq!(book.title(), "Rust Programming");
+/// assert_eq!(book.read_price_raw(), &100);
+/// ```
+///
+/// Affixes must be able to continue an identifier:
+///
+/// ```compile_fail
+/// use smile_marco::Getter;
+/// #[derive(Getter)]
+/// struct Book {
+///     #[getter(prefix = "½")]
+///     price: i32,
+/// }
+/// ```
+///
+/// # Copy getters
+///
+/// `#[getter(copy)]` returns the field by value instead of by reference, the field type must
+/// be `Copy`. With `#[getter(copy_primitives)]` on the struct, primitive types such as `i32`,
+/// `bool`, `f64` or `char` are returned by value. `copy = false` opts a field out.
+///
+/// ```
+/// use smile_marco::Getter;
+/// #[derive(Getter)]
+/// #[getter(copy_primitives)]
+/// struct Book {
+///     title: String,
+///     price: i32,
+///     #[getter(copy = false)]
+///     pages: u32,
+/// }
+///
+/// let book = Book { title: "Rust Programming".to_string(), price: 100, pages: 300 };
+/// assert_eq!(book.get_title(), "Rust Programming");
+/// assert_eq!(book.get_price(), 100);
+/// assert_eq!(book.get_pages(), &300);
+/// ```
+///
+/// # Deref getters
+///
+/// `#[getter(deref)]` returns the borrowed view of common std types: `&str` for `String`,
+/// `&[T]` for `Vec<T>`, `&Path` for `PathBuf`, `&T` for `Box<T>`, `Rc<T>` and `Arc<T>`, and
+/// `Option<&T>` for `Option<T>`. Other types are still returned as `&T`.
+///
+/// ```
+/// use smile_marco::Getter;
+/// #[derive(Getter)]
+/// #[getter(deref)]
+/// struct Book {
+///     title: String,
+///     tags: Vec<String>,
+///     subtitle: Option<String>,
+///     price: i32,
+/// }
+///
+/// let book = Book {
+///     title: "Rust Programming".to_string(),
+///     tags: vec!["rust".to_string()],
+///     subtitle: None,
+///     price: 100,
+/// };
+/// let title: &str = book.get_title();
+/// let tags: &[String] = book.get_tags();
+/// assert_eq!(title, "Rust Programming");
+/// assert_eq!(tags.len(), 1);
+/// assert_eq!(book.get_subtitle(), None);
+/// assert_eq!(book.get_price(), &100);
+/// ```
+///
+/// # Mutable getters
+///
+/// `#[getter(mut)]` also generates `get_x_mut`, returning `&mut T`. It follows the renamed
+/// name and the configured prefix and suffix.
+///
+/// ```
+/// use smile_marco::Getter;
+/// #[derive(Getter)]
+/// struct Book {
+///     #[getter(mut)]
+///     #[smile(name = "labels")]
+///     tags: Vec<String>,
+/// }
+///
+/// let mut book = Book { tags: Vec::new() };
+/// book.get_labels_mut().push("rust".to_string());
+/// assert_eq!(book.get_labels().len(), 1);
+/// ```
 #[cfg(feature = "getter")]
-#[proc_macro_derive(Getter, attributes(exclude, name))]
+#[proc_macro_derive(Getter, attributes(smile, getter, exclude, name))]
 pub fn getter_derive(input: TokenStream1) -> TokenStream1 {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
     getter::gen_ast(&input)
@@ -103,9 +321,9 @@ pub fn getter_derive(input: TokenStream1) -> TokenStream1 {
 /// #[derive(Setter)]
 /// struct Book {
 ///     title: String,
-///     #[name(cost)] // rename
+///     #[smile(name = "cost")] // rename
 ///     price: i32,
-///     #[exclude] // Do not generate set methods for author
+///     #[smile(skip)] // Do not generate set methods for author
 ///     author: String,
 /// }
 /// // And then you can use it like this.
@@ -116,9 +334,11 @@ pub fn getter_derive(input: TokenStream1) -> TokenStream1 {
 ///  };
 ///
 ///  book.set_cost(200);
-///  assert_eq!(book.get_cost(), &200);
+///  assert_eq!(book.price, 200);
+/// ```
 ///
-/// //Setter Macros automatically generate the following code
+/// Setter Macros automatically generate the following code
+/// ```ignore
 /// impl Book {
 ///     #[inline]
 ///     pub fn set_title(&mut self, title: String) {
@@ -129,11 +349,181 @@ pub fn getter_derive(input: TokenStream1) -> TokenStream1 {
 ///         self.price = cost;
 ///     }
 /// }
+/// ```
+///
+/// # Excluding a field from some derives
+///
+/// `#[smile(skip)]` removes the field from every derive, while `#[smile(skip(setter, wither))]`
+/// only removes it from the listed ones. The deprecated `#[exclude(setter, wither)]` works the
+/// same way.
 ///
 /// ```
-
+/// use smile_marco::{Getter, Setter, Wither};
+/// #[derive(Getter, Setter, Wither)]
+/// struct Book {
+///     #[smile(skip(setter, wither))] // the id can be read but not changed
+///     id: u64,
+///     title: String,
+/// }
+///
+/// let mut book = Book { id: 1, title: "Rust Programming".to_string() };
+/// book.set_title("Rust Book".to_string());
+/// assert_eq!(book.get_id(), &1);
+/// ```
+///
+/// ```compile_fail
+/// use smile_marco::Setter;
+/// #[derive(Setter)]
+/// struct Book {
+///     #[exclude(setter)]
+///     id: u64,
+/// }
+///
+/// let mut book = Book { id: 1 };
+/// book.set_id(2);
+/// ```
+///
+/// # Conversions and chaining
+///
+/// `#[setter(into)]` takes any `impl Into<T>` and `#[setter(chain)]` returns `&mut Self`, so
+/// that several setters can be called in a row.
+///
+/// ```
+/// use smile_marco::Setter;
+/// #[derive(Setter)]
+/// #[setter(into, chain)]
+/// struct Config {
+///     name: String,
+///     port: u16,
+/// }
+///
+/// let mut config = Config { name: String::new(), port: 0 };
+/// config.set_name("server").set_port(80u8);
+/// assert_eq!(config.name, "server");
+/// assert_eq!(config.port, 80);
+/// ```
+///
+/// # Replace and take
+///
+/// `#[setter(replace)]` also generates `replace_x`, which sets the field and returns its
+/// previous value. `#[setter(take)]` generates `take_x`, which returns the value and leaves
+/// `Default::default()` in its place. These extra methods, like the `update` and `collection`
+/// ones below, keep their own prefix but follow the configured suffix.
+///
+/// ```
+/// use smile_marco::Setter;
+/// #[derive(Setter)]
+/// struct Machine {
+///     #[setter(replace, take)]
+///     state: String,
+///     #[setter(take, suffix = "_raw")]
+///     code: u32,
+/// }
+///
+/// let mut machine = Machine { state: "idle".to_string(), code: 7 };
+/// assert_eq!(machine.replace_state("running".to_string()), "idle");
+/// assert_eq!(machine.take_state(), "running");
+/// assert!(machine.state.is_empty());
+/// machine.set_code_raw(3);
+/// assert_eq!(machine.take_code_raw(), 3);
+/// ```
+///
+/// # Update in place
+///
+/// `#[setter(update)]` generates `update_x`, which runs a closure on `&mut T`.
+/// `#[setter(update_with)]` generates `update_with_x`, which replaces the value by a closure of
+/// it and needs `T: Default`. Both return `&mut Self`, and neither clashes with the `map_x`
+/// withers of `#[wither(value)]`.
+///
+/// ```
+/// use smile_marco::{Setter, Wither};
+/// #[derive(Setter, Wither)]
+/// #[setter(update, update_with)]
+/// #[wither(value)]
+/// struct Book {
+///     tags: Vec<String>,
+///     price: i32,
+/// }
+///
+/// let mut book = Book { tags: Vec::new(), price: 100 };
+/// book.update_tags(|tags| tags.push("rust".to_string()))
+///     .update_with_price(|price| price * 2);
+/// assert_eq!(book.tags, ["rust"]);
+/// assert_eq!(book.price, 200);
+/// let book = book.map_price(|price| price + 1);
+/// assert_eq!(book.price, 201);
+/// ```
+///
+/// # Collections
+///
+/// `#[setter(collection)]` adds item helpers to `Vec`, `VecDeque`, `HashSet`, `BTreeSet`,
+/// `HashMap` and `BTreeMap` fields, and is ignored on other fields:
+/// - every collection gets `extend_tags` and `clear_tags`
+/// - lists get `push_tag` and `remove_tag` (by index)
+/// - sets get `insert_tag`, `remove_tag` and `contains_tag`
+/// - maps get `insert_tag(key, value)` and `remove_tag(&key)`
+///
+/// The item name is given by `collection(singular = "...")`. Without it, the trailing `s` of
+/// the field name (or of its `name`) is removed, so `tags` gives `push_tag` and `values` gives
+/// `push_value`. Names without a trailing `s`, such as `data`, and names ending in `-ses`,
+/// `-xes`, `-zes`, `-ches`, `-shes`, `-ies`, `-ss` or `-us`, such as `statuses` or `boxes`, are
+/// rejected rather than guessed.
+///
+/// ```
+/// use smile_marco::Setter;
+/// use std::collections::HashMap;
+/// #[derive(Setter)]
+/// #[setter(collection)]
+/// struct Book {
+///     tags: Vec<String>,
+///     values: Vec<u8>,
+///     #[setter(collection(singular = "rating"))]
+///     scores: HashMap<String, u8>,
+/// }
+///
+/// let mut book = Book { tags: Vec::new(), values: Vec::new(), scores: HashMap::new() };
+/// book.push_value(1);
+/// book.push_tag("rust".to_string());
+/// book.extend_tags(["programming".to_string()]);
+/// book.insert_rating("alice".to_string(), 5);
+/// assert_eq!(book.tags.len(), 2);
+/// assert_eq!(book.remove_rating(&"alice".to_string()), Some(5));
+/// ```
+///
+/// ```compile_fail
+/// use smile_marco::Setter;
+/// #[derive(Setter)]
+/// struct Order {
+///     #[setter(collection)] // needs `collection(singular = "status")`
+///     statuses: Vec<String>,
+/// }
+/// ```
+///
+/// # Visibility
+///
+/// Generated methods are `pub` by default. `vis = "pub(crate)"` (or any other visibility)
+/// changes it, `vis = "private"` keeps the method private to the module and `vis = "field"`
+/// reuses the visibility of the field. This works the same for `Getter`, `Wither` and
+/// `Builder`.
+///
+/// ```compile_fail
+/// mod shelf {
+///     use smile_marco::Setter;
+///     #[derive(Setter)]
+///     #[setter(vis = "field")]
+///     pub struct Book {
+///         pub title: String,
+///         id: u64,
+///     }
+/// }
+///
+/// fn rename(book: &mut shelf::Book) {
+///     book.set_title("Rust Book".to_string());
+///     book.set_id(2); // private like the field
+/// }
+/// ```
 #[cfg(feature = "setter")]
-#[proc_macro_derive(Setter, attributes(exclude, name))]
+#[proc_macro_derive(Setter, attributes(smile, setter, exclude, name))]
 pub fn setter_derive(input: TokenStream1) -> TokenStream1 {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
     setter::gen_ast(&input)
@@ -146,9 +536,9 @@ pub fn setter_derive(input: TokenStream1) -> TokenStream1 {
 /// #[derive(Wither)]
 /// struct Book {
 ///     title: String,
-///     #[name(cost)] //rename
+///     #[smile(name = "cost")] //rename
 ///     price: i32,
-///     #[exclude] // Do not generate with methods for author
+///     #[smile(skip)] // Do not generate with methods for author
 ///     author: String,
 /// }
 /// // may be used as such
@@ -160,8 +550,10 @@ pub fn setter_derive(input: TokenStream1) -> TokenStream1 {
 ///
 ///  let book = book.with_cost(|v| v+100);
 ///  assert_eq!(book.price, 200);
+/// ```
 ///
-/// //Wither Macros automatically generate the following code
+/// Wither Macros automatically generate the following code
+/// ```ignore
 /// impl Book {
 ///     #[inline]
 ///     pub fn with_title<F>(mut self, func: F) -> Self
@@ -180,11 +572,50 @@ pub fn setter_derive(input: TokenStream1) -> TokenStream1 {
 ///         self
 ///     }
 /// }
+/// ```
 ///
-
+/// # Value withers
+///
+/// With `#[wither(value)]`, `with_x` takes the new value and the closure form is generated as
+/// `map_x`. `map_prefix = "..."` chooses another prefix for the closure form.
+///
+/// ```
+/// use smile_marco::Wither;
+/// #[derive(Wither)]
+/// #[wither(value)]
+/// struct Book {
+///     title: String,
+///     price: i32,
+/// }
+///
+/// let book = Book { title: "Rust Programming".to_string(), price: 100 };
+/// let book = book.with_price(200).map_title(|t| t + " 2nd edition");
+/// assert_eq!(book.price, 200);
+/// assert_eq!(book.title, "Rust Programming 2nd edition");
+/// ```
+///
+/// # Clone withers
+///
+/// `#[wither(clone)]` generates withers taking `&self`, which return a modified clone and
+/// leave the original untouched. The struct must implement `Clone`, otherwise the derive
+/// fails to compile.
+///
+/// ```
+/// use smile_marco::Wither;
+/// use std::sync::Arc;
+/// #[derive(Wither, Clone)]
+/// #[wither(clone, value)]
+/// struct Config {
+///     port: u16,
+/// }
+///
+/// let shared = Arc::new(Config { port: 80 });
+/// let local = shared.with_port(8080);
+/// assert_eq!(shared.port, 80);
+/// assert_eq!(local.port, 8080);
 /// ```
 #[cfg(feature = "wither")]
-#[proc_macro_derive(Wither, attributes(exclude, name))]
+#[proc_macro_derive(Wither, attributes(smile, wither, exclude, name))]
 pub fn wither_derive(input: TokenStream1) -> TokenStream1 {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
     wither::gen_ast(&input)
@@ -209,7 +640,10 @@ pub fn wither_derive(input: TokenStream1) -> TokenStream1 {
 /// .build();
 /// assert_eq!(book.title, "Rust Programming".to_string());
 /// assert_eq!(book.price, 100);
-/// //Builder Macros automatically generate the following code
+/// ```
+///
+/// Builder Macros automatically generate the following code
+/// ```ignore
 /// struct BookBuilder {
 ///     title: Option<String>,
 ///     price: Option<i32>,
@@ -254,11 +688,320 @@ pub fn wither_derive(input: TokenStream1) -> TokenStream1 {
 ///         BookBuilder::new()
 ///     }
 /// }
+/// ```
 ///
-
+/// # Default values
+///
+/// Fields marked `#[builder(default)]` fall back to `Default::default()` when they are not set,
+/// `#[builder(default = "expr")]` evaluates the given expression and
+/// `#[builder(default_fn = path)]` calls the given function instead.
+///
+/// ```
+/// use smile_marco::Builder;
+///
+/// fn default_author() -> String {
+///     "anonymous".to_string()
+/// }
+///
+/// #[derive(Builder)]
+/// struct Book {
+///     title: String,
+///     #[builder(default = "100")]
+///     price: i32,
+///     #[builder(default_fn = default_author)]
+///     author: String,
+///     #[builder(default)]
+///     tags: Vec<String>,
+///     #[builder(default = "vec![1, 2]")]
+///     editions: Vec<u32>,
+/// }
+///
+/// let book = Book::builder().title("Rust Programming".to_string()).build();
+/// assert_eq!(book.price, 100);
+/// assert_eq!(book.author, "anonymous");
+/// assert!(book.tags.is_empty());
+/// assert_eq!(book.editions, [1, 2]);
+/// ```
+///
+/// The expression is checked where it is written:
+///
+/// ```compile_fail
+/// use smile_marco::Builder;
+/// #[derive(Builder)]
+/// struct Book {
+///     #[builder(default = "1 +")]
+///     price: i32,
+/// }
+/// ```
+///
+/// Written on the struct, `#[builder(default)]` makes every field optional. A field marked
+/// `#[builder(required)]` opts out of an inherited default.
+///
+/// ```
+/// use smile_marco::Builder;
+///
+/// #[derive(Builder)]
+/// #[builder(default)]
+/// struct Book {
+///     #[builder(required)]
+///     title: String,
+///     price: i32,
+///     #[builder(default = "\"anonymous\".to_string()")]
+///     author: String,
+/// }
+///
+/// let book = Book::builder().title("Rust Programming".to_string()).build();
+/// assert_eq!(book.price, 0);
+/// assert_eq!(book.author, "anonymous");
+/// assert!(Book::builder().price(100).try_build().is_err());
+/// ```
+///
+/// # Conversions
+///
+/// `#[builder(into)]` makes setters take `impl Into<T>`. With `#[builder(strip_option)]`,
+/// setters of `Option<T>` fields take a plain `T` and the field stays `None` when it is never
+/// set. Both can be written on the struct, `strip_option` then only affects `Option` fields.
+///
+/// ```
+/// use smile_marco::Builder;
+/// #[derive(Builder)]
+/// #[builder(into, strip_option)]
+/// struct Book {
+///     title: String,
+///     pages: Option<u32>,
+///     subtitle: Option<String>,
+/// }
+///
+/// let book = Book::builder().title("Rust Programming").pages(300u16).build();
+/// assert_eq!(book.title, "Rust Programming");
+/// assert_eq!(book.pages, Some(300));
+/// assert_eq!(book.subtitle, None);
+/// ```
+///
+/// # Collections
+///
+/// `#[builder(each = "tag")]` on a `Vec`, `VecDeque`, `HashSet`, `BTreeSet`, `HashMap` or
+/// `BTreeMap` field generates a `tag` method adding one item, or one key and value for maps.
+/// The collection starts empty when it is never set. If the item method has the same name as
+/// the field, it replaces the setter of the whole collection.
+///
+/// ```
+/// use smile_marco::Builder;
+/// use std::collections::BTreeMap;
+/// #[derive(Builder)]
+/// struct Book {
+///     title: String,
+///     #[builder(each = "tag")]
+///     tags: Vec<String>,
+///     #[builder(each = "meta")]
+///     meta: BTreeMap<String, String>,
+/// }
+///
+/// let book = Book::builder()
+///     .title("Rust Programming".to_string())
+///     .tag("rust".to_string())
+///     .tag("programming".to_string())
+///     .meta("isbn".to_string(), "123".to_string())
+///     .build();
+/// assert_eq!(book.tags, ["rust", "programming"]);
+/// assert_eq!(book.meta["isbn"], "123");
+/// ```
+///
+/// # Visibility
+///
+/// The builder type has the visibility of the struct and its setters are `pub`. On the
+/// struct, `#[builder(vis = "...")]` changes the visibility of the builder type, of
+/// `builder()` and, unless a field overrides it, of every setter.
+///
+/// ```
+/// mod shelf {
+///     use smile_marco::Builder;
+///     #[derive(Builder)]
+///     #[builder(vis = "pub(crate)")]
+///     pub struct Book {
+///         pub title: String,
+///         #[builder(vis = "private", default)]
+///         id: u64,
+///     }
+/// }
+///
+/// let book = shelf::Book::builder().title("Rust Programming".to_string()).build();
+/// assert_eq!(book.title, "Rust Programming");
+/// ```
+///
+/// # Rename and exclude
+///
+/// Like the other derives, `#[smile(name = "...")]` renames the setter of a field and
+/// `#[smile(skip)]` removes it from the builder, in which case the field is filled from its
+/// declared default or from `Default::default()`.
+///
+/// ```
+/// use smile_marco::Builder;
+/// #[derive(Builder)]
+/// struct Book {
+///     title: String,
+///     #[smile(name = "cost")]
+///     price: i32,
+///     #[smile(skip, builder(default = "\"rust\".to_string()"))]
+///     author: String,
+///     #[smile(skip)]
+///     sales: u64,
+/// }
+///
+/// let book = Book::builder()
+///     .title("Rust Programming".to_string())
+///     .cost(100)
+///     .build();
+/// assert_eq!(book.price, 100);
+/// assert_eq!(book.author, "rust");
+/// assert_eq!(book.sales, 0);
+/// ```
+///
+/// # Tuple structs
+///
+/// Fields of a tuple struct get positional setters such as `_0`, or the name given with
+/// `#[smile(name = "...")]`.
+///
+/// ```
+/// use smile_marco::Builder;
+/// #[derive(Builder)]
+/// struct Point(f64, #[smile(name = "y")] f64);
+///
+/// let point = Point::builder()._0(1.0).y(2.0).build();
+/// assert_eq!(point.1, 2.0);
+/// ```
+///
+/// # Fallible build
+///
+/// `try_build` reports every field that was not set through the generated `BookBuilderError`
+/// instead of panicking like `build`. Neither needs `std` or `alloc`.
+///
+/// ```
+/// use smile_marco::Builder;
+/// #[derive(Builder)]
+/// struct Book {
+///     title: String,
+///     price: i32,
+///     author: String,
+/// }
+///
+/// let err = Book::builder().price(100).try_build().err().unwrap();
+/// assert_eq!(err.missing_fields(), ["title", "author"]);
+/// assert_eq!(err.to_string(), "missing fields in Book builder: title, author");
+///
+/// let book = Book::builder()
+///     .title("Rust Programming".to_string())
+///     .price(100)
+///     .author("rust".to_string())
+///     .try_build()
+///     .unwrap();
+/// assert_eq!(book.price, 100);
+/// ```
+///
+/// # Type-state builder
+///
+/// With `#[builder(typestate)]` every required field, one without a default, is tracked in the
+/// type parameters of the builder, so `build` only exists once all required fields are set and
+/// a missing one is a compile error instead of a panic at runtime.
+///
+/// ```
+/// use smile_marco::Builder;
+/// #[derive(Builder)]
+/// #[builder(typestate)]
+/// struct Book {
+///     title: String,
+///     price: i32,
+/// }
+///
+/// let book = Book::builder()
+///     .price(100)
+///     .title("Rust Programming".to_string())
+///     .build();
+/// assert_eq!(book.price, 100);
+/// ```
+///
+/// Fields with a default are not tracked and may be left unset.
+///
+/// ```
+/// use smile_marco::Builder;
+/// #[derive(Builder)]
+/// #[builder(typestate)]
+/// struct Book {
+///     title: String,
+///     #[builder(default)]
+///     price: i32,
+/// }
+///
+/// let book = Book::builder().title("Rust Programming".to_string()).build();
+/// assert_eq!(book.price, 0);
+/// ```
+///
+/// Any field name works, including raw identifiers and names used inside the builder.
+///
+/// ```
+/// use smile_marco::Builder;
+/// #[derive(Builder)]
+/// #[builder(typestate)]
+/// struct Item {
+///     r#type: String,
+///     foo_bar: u8,
+///     foo__bar: u8,
+///     __marker: u8,
+/// }
+///
+/// let item = Item::builder()
+///     .r#type("book".to_string())
+///     .foo_bar(1)
+///     .foo__bar(2)
+///     .__marker(3)
+///     .build();
+/// assert_eq!(item.r#type, "book");
+/// ```
+///
+/// Lifetimes, bounded and defaulted type parameters and const generics are kept next to the
+/// state parameters.
+///
+/// ```
+/// use smile_marco::Builder;
+/// use std::fmt::Debug;
+/// #[derive(Builder)]
+/// #[builder(typestate)]
+/// struct Page<'a, T: Debug = u8, const N: usize = 2>
+/// where
+///     T: Default,
+/// {
+///     title: &'a str,
+///     #[builder(default)]
+///     item: T,
+///     lines: [u8; N],
+/// }
+///
+/// let page: Page = Page::builder().lines([1, 2]).title("Rust").build();
+/// assert_eq!(page.item, 0);
+///
+/// let page = Page::<String, 3>::builder()
+///     .title("Rust")
+///     .item("rust".to_string())
+///     .lines([1, 2, 3])
+///     .build();
+/// assert_eq!(page.item, "rust");
+/// assert_eq!(page.lines.len(), 3);
+/// ```
+///
+/// ```compile_fail
+/// use smile_marco::Builder;
+/// #[derive(Builder)]
+/// #[builder(typestate)]
+/// struct Book {
+///     title: String,
+///     price: i32,
+/// }
+///
+/// // `price` is never set, so there is no `build` method to call
+/// let book = Book::builder().title("Rust Programming".to_string()).build();
 /// ```
 #[cfg(feature = "builder")]
-#[proc_macro_derive(Builder)]
+#[proc_macro_derive(Builder, attributes(smile, builder, exclude, name))]
 pub fn builder_derive(input: TokenStream1) -> TokenStream1 {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
     builder::gen_ast(&input)
@@ -273,17 +1016,48 @@ pub fn builder_derive(input: TokenStream1) -> TokenStream1 {
 ///     price: i32,
 ///     author: String,
 /// }
-/// // data macro automatically adds to structure `Getter,Setter,Wither,Builder` macros
-/// #[derive(Getter,Setter,Wither,Builder)]
+/// ```
+///
+/// data macro automatically adds to structure `Getter,Setter,Wither,Builder` macros
+/// ```ignore
+/// #[derive(smile_marco::Getter, smile_marco::Setter, smile_marco::Wither, smile_marco::Builder)]
 /// struct Book {
 ///     title: String,
 ///     price: i32,
 ///     author: String,
 /// }
-/// // You can also use the link attribute to specify the source of macros such as Getter、Setter
-/// // and use the exclude attribute to exclude macros
+/// ```
+///
+/// You can also use the link attribute to specify the source of macros such as Getter、Setter,
+/// the exclude attribute to exclude macros, the include attribute to keep only the listed
+/// macros and the derive attribute to add other derives such as `Debug` or `Clone`
+/// ```ignore
 /// #[data(link="xxx",exclude = ["Getter", "Setter"])]
 /// ```
+///
+/// ```
+/// use smile_marco::data;
+/// #[data(exclude = [Setter, Wither], derive = [Debug, Clone, PartialEq])]
+/// struct Book {
+///     title: String,
+///     price: i32,
+/// }
+///
+/// let book = Book::builder().title("Rust Programming".to_string()).price(100).build();
+/// assert_eq!(book.get_price(), &100);
+/// assert_eq!(book.clone(), book);
+/// ```
+///
+/// ```
+/// use smile_marco::data;
+/// #[data(include = ["Getter"])]
+/// struct Book {
+///     title: String,
+/// }
+///
+/// let book = Book { title: "Rust Programming".to_string() };
+/// assert_eq!(book.get_title(), "Rust Programming");
+/// ```
 #[cfg(feature = "full")]
 #[proc_macro_attribute]
 pub fn data(attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
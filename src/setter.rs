@@ -1,35 +1,286 @@
-use crate::util::StructContext;
-use crate::TokenStream1;
+use crate::util::{
+    collect_results, deprecated_attrs, parse_flag, Collection, DeriveOptions, FieldContext,
+    MacroKind, MethodName, MethodVis, StructContext,
+};
+use crate::{TokenStream1, TokenStream2};
 use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
 use syn::spanned::Spanned;
-use syn::DeriveInput;
+use syn::{DeriveInput, Ident, LitStr, Token};
+
+/// Options declared through `#[setter(...)]`, fields inherit the ones set on the struct
+#[derive(Default, Clone)]
+struct SetterOptions {
+    method: MethodName,
+    vis: MethodVis,
+    // take `impl Into<T>` instead of `T`
+    into: bool,
+    // return `&mut Self` so that setters can be chained
+    chain: bool,
+    // also generate `replace_x`, returning the previous value
+    replace: bool,
+    // also generate `take_x`, leaving `Default::default()` in place
+    take: bool,
+    // also generate `update_x`, running a closure on `&mut T`
+    update: bool,
+    // also generate `update_with_x`, replacing the value by a closure of it, for `Default` types
+    update_with: bool,
+    // also generate item helpers such as `push_x` on collection fields
+    collection: bool,
+    // name of one item in the helpers, derived from the field name by default
+    singular: Option<Ident>,
+}
+
+impl DeriveOptions for SetterOptions {
+    fn parse_option(&mut self, meta: &ParseNestedMeta, _: bool) -> syn::Result<()> {
+        if self.method.parse_option(meta)? || self.vis.parse_option(meta)? {
+            Ok(())
+        } else if meta.path.is_ident("into") {
+            self.into = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("chain") {
+            self.chain = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("replace") {
+            self.replace = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("take") {
+            self.take = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("update") {
+            self.update = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("update_with") {
+            self.update_with = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("collection") {
+            if meta.input.peek(Token![=]) {
+                self.collection = parse_flag(meta)?;
+                return Ok(());
+            }
+            self.collection = true;
+            if meta.input.is_empty() || meta.input.peek(Token![,]) {
+                return Ok(());
+            }
+            meta.parse_nested_meta(|meta| {
+                if meta.path.is_ident("singular") {
+                    self.singular = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported collection attribute, expected `singular`"))
+                }
+            })
+        } else {
+            Err(meta.error(
+                "unsupported setter attribute, expected `prefix`, `suffix`, `vis`, `into`, `chain`, `replace`, `take`, `update`, `update_with` or `collection`",
+            ))
+        }
+    }
+}
 
 pub(crate) fn gen_ast(input: &DeriveInput) -> TokenStream1 {
-    let context = StructContext::new(input);
+    let mut ast = expand(input).unwrap_or_else(syn::Error::into_compile_error);
+    ast.extend(deprecated_attrs(input));
+    ast.into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let context = StructContext::new(input)?;
     let impl_sign = context.impl_sign();
-    let fields = context.handle_fields_exclude_and_name_attr();
+    let fields = context.handle_fields_exclude_and_name_attr::<SetterOptions>(MacroKind::Setter)?;
 
     let gen_fns = fields
         .iter()
         .map(|fc| {
-            let var_name = format_ident!("{}", &fc.name);
+            // positional fields have no name usable as a parameter
+            let var_name =
+                syn::parse_str::<Ident>(&fc.name).unwrap_or_else(|_| format_ident!("value"));
             let ty = fc.ty;
-            let ident = fc.ident;
-            let mut fn_name = format_ident!("set_{}", &var_name);
-            fn_name.set_span(fc.ident.span());
-            quote! {
-                 #[inline]
-                pub fn #fn_name (&mut self,#var_name:#ty){
-                    self.#ident = #var_name;
+            let ident = &fc.member;
+            let fn_name = fc.options.method.ident("set_", &fc.name, fc.member.span());
+            let vis = fc.options.vis.resolve(quote!(pub), fc.vis);
+            let (param_ty, value) = if fc.options.into {
+                (
+                    quote!(impl ::core::convert::Into<#ty>),
+                    quote!(::core::convert::Into::into(#var_name)),
+                )
+            } else {
+                (quote!(#ty), quote!(#var_name))
+            };
+            let mut setter = if fc.options.chain {
+                quote! {
+                    #[inline]
+                    #vis fn #fn_name (&mut self,#var_name:#param_ty) -> &mut Self {
+                        self.#ident = #value;
+                        self
+                    }
+                }
+            } else {
+                quote! {
+                    #[inline]
+                    #vis fn #fn_name (&mut self,#var_name:#param_ty){
+                        self.#ident = #value;
+                    }
                 }
+            };
+            let span = fc.member.span();
+            if fc.options.replace {
+                let replace_name = fc.options.method.ident_with_prefix("replace_", &fc.name, span);
+                setter.extend(quote! {
+                    #[inline]
+                    #vis fn #replace_name (&mut self,#var_name:#param_ty) -> #ty {
+                        ::core::mem::replace(&mut self.#ident, #value)
+                    }
+                });
+            }
+            if fc.options.take {
+                let take_name = fc.options.method.ident_with_prefix("take_", &fc.name, span);
+                setter.extend(quote! {
+                    #[inline]
+                    #vis fn #take_name (&mut self) -> #ty {
+                        ::core::mem::take(&mut self.#ident)
+                    }
+                });
+            }
+            if fc.options.update {
+                let update_name = fc.options.method.ident_with_prefix("update_", &fc.name, span);
+                setter.extend(quote! {
+                    #[inline]
+                    #vis fn #update_name (&mut self, func: impl ::core::ops::FnOnce(&mut #ty)) -> &mut Self {
+                        func(&mut self.#ident);
+                        self
+                    }
+                });
+            }
+            if fc.options.update_with {
+                // the value is taken out while the closure runs, leaving a default behind
+                let update_with_name =
+                    fc.options.method.ident_with_prefix("update_with_", &fc.name, span);
+                setter.extend(quote! {
+                    #[inline]
+                    #vis fn #update_with_name (&mut self, func: impl ::core::ops::FnOnce(#ty) -> #ty) -> &mut Self {
+                        self.#ident = func(::core::mem::take(&mut self.#ident));
+                        self
+                    }
+                });
             }
-        })
-        .collect::<Vec<_>>();
+            if fc.options.collection {
+                setter.extend(collection_helpers(fc, &vis)?);
+            }
+            Ok(setter)
+        });
+    let gen_fns = collect_results(gen_fns)?;
 
     let ast = quote! {
         #impl_sign {
             #(#gen_fns)*
         }
     };
-    ast.into()
+    Ok(ast)
+}
+
+/// Item helpers of a `Vec`, `VecDeque`, `HashSet`, `BTreeSet`, `HashMap` or `BTreeMap` field,
+/// nothing for other types so that `collection` can be set on the whole struct
+fn collection_helpers(
+    fc: &FieldContext<SetterOptions>,
+    vis: &TokenStream2,
+) -> syn::Result<TokenStream2> {
+    let Some(collection) = Collection::of(fc.ty) else {
+        return Ok(TokenStream2::new());
+    };
+    let span = fc.member.span();
+    let ident = &fc.member;
+    let singular = match fc.options.singular {
+        Some(ref singular) => singular.to_string(),
+        None => singular_name(&fc.name).ok_or_else(|| {
+            syn::Error::new(
+                span,
+                format!(
+                    "cannot guess the item name of `{}`, set it with `#[setter(collection(singular = \"...\"))]`",
+                    fc.name
+                ),
+            )
+        })?,
+    };
+    let name = |prefix: &str, name: &str| fc.options.method.ident_with_prefix(prefix, name, span);
+    let extend_name = name("extend_", &fc.name);
+    let clear_name = name("clear_", &fc.name);
+    let (insert_name, remove_name) = match collection {
+        Collection::List { .. } => (name("push_", &singular), name("remove_", &singular)),
+        _ => (name("insert_", &singular), name("remove_", &singular)),
+    };
+    let (item, helpers) = match collection {
+        Collection::List { item, deque } => {
+            let (push, removed) = if deque {
+                (quote!(push_back), quote!(::core::option::Option<#item>))
+            } else {
+                (quote!(push), quote!(#item))
+            };
+            let helpers = quote! {
+                #[inline]
+                #vis fn #insert_name (&mut self, item: #item) {
+                    self.#ident.#push(item);
+                }
+                #[inline]
+                #vis fn #remove_name (&mut self, index: usize) -> #removed {
+                    self.#ident.remove(index)
+                }
+            };
+            (quote!(#item), helpers)
+        }
+        Collection::Set { item } => {
+            let contains_name = name("contains_", &singular);
+            let helpers = quote! {
+                #[inline]
+                #vis fn #insert_name (&mut self, item: #item) -> bool {
+                    self.#ident.insert(item)
+                }
+                #[inline]
+                #vis fn #remove_name (&mut self, item: &#item) -> bool {
+                    self.#ident.remove(item)
+                }
+                #[inline]
+                #vis fn #contains_name (&self, item: &#item) -> bool {
+                    self.#ident.contains(item)
+                }
+            };
+            (quote!(#item), helpers)
+        }
+        Collection::Map { key, value } => {
+            let helpers = quote! {
+                #[inline]
+                #vis fn #insert_name (&mut self, key: #key, value: #value) -> ::core::option::Option<#value> {
+                    self.#ident.insert(key, value)
+                }
+                #[inline]
+                #vis fn #remove_name (&mut self, key: &#key) -> ::core::option::Option<#value> {
+                    self.#ident.remove(key)
+                }
+            };
+            (quote!((#key, #value)), helpers)
+        }
+    };
+    Ok(quote! {
+        #helpers
+        #[inline]
+        #vis fn #extend_name (&mut self, items: impl ::core::iter::IntoIterator<Item = #item>) {
+            self.#ident.extend(items);
+        }
+        #[inline]
+        #vis fn #clear_name (&mut self) {
+            self.#ident.clear();
+        }
+    })
+}
+
+/// Name of one item of a collection named `name` by removing its trailing `s`, such as `tag`
+/// for `tags` or `value` for `values`. Endings where that is ambiguous, such as `statuses`,
+/// `boxes` or `movies`, and names without a trailing `s` are not guessed
+fn singular_name(name: &str) -> Option<String> {
+    let ambiguous = ["ses", "xes", "zes", "ches", "shes", "ies", "ss", "us"];
+    if ambiguous.iter().any(|end| name.ends_with(end)) {
+        return None;
+    }
+    let stem = name.strip_suffix('s')?;
+    (!stem.is_empty()).then(|| stem.to_string())
 }
@@ -1,138 +1,749 @@
-use proc_macro2::Ident;
+use proc_macro2::{Ident, Span};
 use quote::quote;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use syn::meta::ParseNestedMeta;
 use syn::punctuated::Punctuated;
-use syn::{DeriveInput, Field, Fields, Generics, Token, Type, Visibility, WhereClause};
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, DeriveInput, Field, Fields, GenericArgument, Generics, Index, LitBool, LitStr,
+    Member, PathArguments, Token, Type, Variant, Visibility, WhereClause,
+};
 
 use crate::TokenStream2;
 
-pub struct FieldContext<'a> {
+pub struct FieldContext<'a, O = ()> {
+    // field visibility
+    pub vis: &'a Visibility,
     // field type
     pub ty: &'a Type,
-    // field name
-    pub ident: Option<&'a Ident>,
+    // field name, or its index in a tuple struct
+    pub member: Member,
     // field new name
     pub name: String,
+    // options of the derive, inherited from the struct and overridden by the field
+    pub options: O,
 }
 
-impl<'a> FieldContext<'a> {
-    pub fn new(ty: &'a Type, ident: Option<&'a Ident>, name: String) -> Self {
-        Self { ty, ident, name }
+impl<'a, O> FieldContext<'a, O> {
+    pub fn new(
+        vis: &'a Visibility,
+        ty: &'a Type,
+        member: Member,
+        name: String,
+        options: O,
+    ) -> Self {
+        Self {
+            vis,
+            ty,
+            member,
+            name,
+            options,
+        }
     }
 }
 
 pub(crate) struct StructContext<'a> {
+    pub attrs: &'a [Attribute],
     pub vis: &'a Visibility,
     pub ident: &'a Ident,
     pub generics: &'a Generics,
     pub where_case: Option<&'a WhereClause>,
-    pub fields: &'a Punctuated<Field, Token![,]>,
+    pub fields: &'a Fields,
 }
 
 impl<'a> StructContext<'a> {
-    pub fn new(input: &'a DeriveInput) -> Self {
+    pub fn new(input: &'a DeriveInput) -> syn::Result<Self> {
         let vis = &input.vis;
         let ident = &input.ident;
         let generics = &input.generics;
         let where_case = generics.where_clause.as_ref();
         let fields = match &input.data {
             syn::Data::Struct(s) => &s.fields,
-            _ => panic!("not a struct"),
-        };
-        let fields = match fields {
-            Fields::Named(f) => &f.named,
-            _ => panic!("not a named struct"),
+            syn::Data::Enum(e) => {
+                return Err(syn::Error::new(
+                    e.enum_token.span,
+                    "expected a struct, enums are not supported",
+                ))
+            }
+            syn::Data::Union(u) => {
+                return Err(syn::Error::new(
+                    u.union_token.span,
+                    "expected a struct, unions are not supported",
+                ))
+            }
         };
-        Self {
+        Ok(Self {
+            attrs: &input.attrs,
             vis,
             ident,
             generics,
             where_case,
             fields,
-        }
+        })
     }
     #[allow(unused_doc_comments)]
     /// Returns the block signature of impl
     pub fn impl_sign(&self) -> TokenStream2 {
-        let ident = self.ident;
-        let generics = self.generics;
-        let where_case = &generics.where_clause;
-        quote! {
-            impl #generics #ident #generics #where_case
-        }
+        impl_sign(self.ident, self.generics)
     }
     // This method deals specifically with the processing of exclude and name attributes
 
-    pub fn handle_fields_exclude_and_name_attr(&self) -> Vec<FieldContext> {
+    pub fn handle_fields_exclude_and_name_attr<O: DeriveOptions>(
+        &self,
+        kind: MacroKind,
+    ) -> syn::Result<Vec<FieldContext<'_, O>>> {
+        let (_, attrs) = self.parse_fields_attrs::<O>(kind)?;
         let fields = self
             .fields
             .iter()
-            .filter(|f| {
-                for attr in &f.attrs {
-                    if let syn::Meta::Path(ref path) = attr.meta {
-                        if path.is_ident("exclude") {
-                            return false;
-                        }
-                    }
-                }
-                true
+            .zip(attrs)
+            .enumerate()
+            .filter(|(_, (_, attrs))| !attrs.exclude)
+            .map(|(i, (f, attrs))| {
+                FieldContext::new(&f.vis, &f.ty, field_member(f, i), attrs.name, attrs.options)
+            })
+            .collect::<Vec<_>>();
+        Ok(fields)
+    }
+
+    /// Reads the options of the derive `kind` declared on the struct, and the attributes of
+    /// every field in declaration order. Each field starts from the struct options, so options
+    /// declared on a field override the ones declared on the struct
+    pub fn parse_fields_attrs<O: DeriveOptions>(
+        &self,
+        kind: MacroKind,
+    ) -> syn::Result<(O, Vec<FieldAttrs<O>>)> {
+        let options = parse_struct_attrs::<O>(self.attrs, kind);
+        let inherited = options.as_ref().cloned().unwrap_or_default();
+        let fields = collect_results(
+            self.fields
+                .iter()
+                .enumerate()
+                .map(|(i, f)| parse_field_attrs(f, i, kind, inherited.clone())),
+        );
+        match (options, fields) {
+            (Ok(options), Ok(fields)) => Ok((options, fields)),
+            (Err(mut err), Err(fields_err)) => {
+                err.combine(fields_err);
+                Err(err)
+            }
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        }
+    }
+}
+
+/// The derive macro that is generating code
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MacroKind {
+    Getter,
+    Setter,
+    Wither,
+    Builder,
+}
+
+impl MacroKind {
+    const ALL: [MacroKind; 4] = [
+        MacroKind::Getter,
+        MacroKind::Setter,
+        MacroKind::Wither,
+        MacroKind::Builder,
+    ];
+
+    /// Lowercase name used to refer to the derive inside attributes
+    pub fn name(self) -> &'static str {
+        match self {
+            MacroKind::Getter => "getter",
+            MacroKind::Setter => "setter",
+            MacroKind::Wither => "wither",
+            MacroKind::Builder => "builder",
+        }
+    }
+
+    pub fn from_path(path: &syn::Path) -> syn::Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| path.is_ident(kind.name()))
+            .ok_or_else(|| {
+                syn::Error::new_spanned(
+                    path,
+                    "unknown derive, expected `getter`, `setter`, `wither` or `builder`",
+                )
             })
-            .map(|f| {
-                for attr in &f.attrs {
-                    if let syn::Meta::List(ref list) = attr.meta {
-                        let option = list.path.segments.first();
-                        if let Some(v) = option {
-                            if v.ident.to_string().eq("name") {
-                                if list.tokens.is_empty() {
-                                    panic!("name is empty");
-                                } else {
-                                    let new_attr_name = list.tokens.to_string();
-                                    if !new_attr_name.contains(",") {
-                                        return FieldContext::new(
-                                            &f.ty,
-                                            f.ident.as_ref(),
-                                            new_attr_name,
-                                        );
-                                    } else {
-                                        panic!(
-                                            "Only one value is required for the name on property"
-                                        )
-                                    }
-                                }
-                            }
+    }
+}
+
+/// A named field found in one or more variants of an enum
+pub struct VariantFieldContext<'a, O = ()> {
+    // field type, the same in every variant
+    pub ty: &'a Type,
+    // field new name
+    pub name: String,
+    // span of the first declaration of the field
+    pub span: Span,
+    // variants holding the field, with the field name inside each of them
+    pub variants: Vec<(&'a Ident, Member)>,
+    // whether every variant of the enum holds the field
+    pub shared: bool,
+    // options of the derive, taken from the first declaration of the field
+    pub options: O,
+}
+
+pub(crate) struct EnumContext<'a> {
+    pub attrs: &'a [Attribute],
+    pub vis: &'a Visibility,
+    pub ident: &'a Ident,
+    pub generics: &'a Generics,
+    pub variants: &'a Punctuated<Variant, Token![,]>,
+}
+
+impl<'a> EnumContext<'a> {
+    pub fn new(input: &'a DeriveInput) -> syn::Result<Self> {
+        let variants = match &input.data {
+            syn::Data::Enum(e) => &e.variants,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "expected an enum")),
+        };
+        Ok(Self {
+            attrs: &input.attrs,
+            vis: &input.vis,
+            ident: &input.ident,
+            generics: &input.generics,
+            variants,
+        })
+    }
+
+    /// Returns the block signature of impl
+    pub fn impl_sign(&self) -> TokenStream2 {
+        impl_sign(self.ident, self.generics)
+    }
+
+    /// Groups the named fields of all variants by their (possibly renamed) name, skipping
+    /// excluded fields. A name must have the same type in every variant that declares it.
+    /// Options declared on the enum are inherited by every field
+    pub fn handle_variant_fields_exclude_and_name_attr<O: DeriveOptions>(
+        &self,
+        kind: MacroKind,
+    ) -> syn::Result<Vec<VariantFieldContext<'_, O>>> {
+        let mut fields: Vec<VariantFieldContext<O>> = Vec::new();
+        let mut error: Option<syn::Error> = None;
+        let inherited = parse_struct_attrs::<O>(self.attrs, kind).unwrap_or_else(|err| {
+            combine_error(&mut error, err);
+            O::default()
+        });
+        for variant in self.variants {
+            let named = match variant.fields {
+                Fields::Named(ref named) => &named.named,
+                _ => continue,
+            };
+            for (i, field) in named.iter().enumerate() {
+                let (name, options) = match parse_field_attrs(field, i, kind, inherited.clone()) {
+                    Ok(attrs) if attrs.exclude => continue,
+                    Ok(attrs) => (attrs.name, attrs.options),
+                    Err(err) => {
+                        combine_error(&mut error, err);
+                        continue;
+                    }
+                };
+                let member = field_member(field, i);
+                match fields.iter_mut().find(|f| f.name == name) {
+                    Some(existing) => {
+                        let (ty, other) = (&field.ty, existing.ty);
+                        if quote!(#ty).to_string() != quote!(#other).to_string() {
+                            let err = syn::Error::new_spanned(
+                                ty,
+                                format!("field `{}` has a different type in another variant", name),
+                            );
+                            combine_error(&mut error, err);
+                            continue;
                         }
+                        existing.variants.push((&variant.ident, member));
                     }
+                    None => fields.push(VariantFieldContext {
+                        ty: &field.ty,
+                        name,
+                        span: member.span(),
+                        variants: vec![(&variant.ident, member)],
+                        shared: false,
+                        options,
+                    }),
                 }
-                let attr_name = f
-                    .ident
-                    .as_ref()
-                    .expect("attr cannot be without signatures")
-                    .to_string();
-                FieldContext::new(&f.ty, f.ident.as_ref(), attr_name)
-            })
-            .collect::<Vec<_>>();
-        fields
+            }
+        }
+        if let Some(error) = error {
+            return Err(error);
+        }
+        for field in fields.iter_mut() {
+            field.shared = field.variants.len() == self.variants.len();
+        }
+        Ok(fields)
+    }
+}
+
+/// Returns the block signature of impl for the given type
+fn impl_sign(ident: &Ident, generics: &Generics) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_case) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #ident #ty_generics #where_case
+    }
+}
+
+/// Accesses the field by name, or by its position `index` in a tuple struct
+pub(crate) fn field_member(field: &Field, index: usize) -> Member {
+    match field.ident {
+        Some(ref ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(Index {
+            index: index as u32,
+            span: field.ty.span(),
+        }),
+    }
+}
+
+/// Options a derive reads from `#[smile(<kind>(...))]` or from the shorter `#[<kind>(...)]`
+pub(crate) trait DeriveOptions: Default + Clone {
+    /// Parses one entry of the option list, declared on a field if `on_field` is set and on
+    /// the struct otherwise
+    fn parse_option(&mut self, meta: &ParseNestedMeta, on_field: bool) -> syn::Result<()>;
+}
+
+impl DeriveOptions for () {
+    fn parse_option(&mut self, meta: &ParseNestedMeta, _: bool) -> syn::Result<()> {
+        Err(meta.error("unsupported option, this derive has no options"))
     }
 }
 
-pub(crate) mod token_tree_utils {
-    use crate::TokenStream1;
-    use proc_macro2::TokenTree;
+/// Attributes of a field for the derive being expanded
+pub(crate) struct FieldAttrs<O> {
+    // name used for the generated methods, the field name (or index) unless renamed
+    pub name: String,
+    pub exclude: bool,
+    pub options: O,
+}
+
+/// Reads the attributes of a field for the derive `kind`, starting from `options`.
+///
+/// Besides `#[smile(name = "...", skip(...), <kind>(...))]` and `#[<kind>(...)]`, the bare
+/// `#[name(...)]` and `#[exclude]` forms are still accepted as deprecated aliases.
+/// Fields of a tuple struct are named after their position, e.g. `0`.
+pub(crate) fn parse_field_attrs<O: DeriveOptions>(
+    field: &Field,
+    index: usize,
+    kind: MacroKind,
+    options: O,
+) -> syn::Result<FieldAttrs<O>> {
+    let mut parser = AttrParser {
+        kind,
+        field_level: true,
+        name: None,
+        exclude: false,
+        options,
+    };
+    parser.parse(&field.attrs)?;
+    let name = match (parser.name, &field.ident) {
+        (Some(name), _) => name.to_string(),
+        (None, Some(ident)) => ident.to_string(),
+        (None, None) => index.to_string(),
+    };
+    Ok(FieldAttrs {
+        name,
+        exclude: parser.exclude,
+        options: parser.options,
+    })
+}
+
+/// Reads the options declared on the struct itself for the derive `kind`
+pub(crate) fn parse_struct_attrs<O: DeriveOptions>(
+    attrs: &[Attribute],
+    kind: MacroKind,
+) -> syn::Result<O> {
+    let mut parser = AttrParser {
+        kind,
+        field_level: false,
+        name: None,
+        exclude: false,
+        options: O::default(),
+    };
+    parser.parse(attrs)?;
+    Ok(parser.options)
+}
 
-    #[inline]
-    pub(crate) fn punct_eq<'a>(punct: Option<&'a TokenTree>, eq: &'a str) -> bool {
-        if let Some(TokenTree::Punct(ref v)) = punct {
-            return v.to_string().eq(eq);
+/// Shared parser of the attributes understood by every derive
+struct AttrParser<O> {
+    kind: MacroKind,
+    // `name` and `skip` only make sense on fields
+    field_level: bool,
+    name: Option<Ident>,
+    exclude: bool,
+    options: O,
+}
+
+impl<O: DeriveOptions> AttrParser<O> {
+    fn parse(&mut self, attrs: &[Attribute]) -> syn::Result<()> {
+        let mut error = None;
+        for attr in attrs {
+            let result = if attr.path().is_ident("smile") {
+                attr.parse_nested_meta(|meta| self.parse_smile(&meta))
+            } else if attr.path().is_ident(self.kind.name()) {
+                attr.parse_nested_meta(|meta| self.options.parse_option(&meta, self.field_level))
+            } else if !self.field_level || !is_deprecated_alias(attr) {
+                Ok(())
+            } else if attr.path().is_ident("exclude") {
+                self.parse_exclude(attr)
+            } else if let syn::Meta::List(ref list) = attr.meta {
+                self.parse_name(list)
+            } else {
+                Ok(())
+            };
+            if let Err(err) = result {
+                combine_error(&mut error, err);
+            }
+        }
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
         }
-        false
     }
 
-    #[inline]
-    pub(crate) fn is_struct(input: &TokenStream1) -> bool {
-        for x in input.clone().into_iter() {
-            if let proc_macro::TokenTree::Ident(ref v) = x {
-                return v.to_string().eq("struct");
+    /// One entry of `#[smile(...)]`
+    fn parse_smile(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("name") {
+            if !self.field_level {
+                return Err(meta.error("`name` can only be used on fields"));
+            }
+            let value = meta.value()?;
+            self.name = Some(if value.peek(LitStr) {
+                value.parse::<LitStr>()?.parse()?
+            } else {
+                value.parse()?
+            });
+            Ok(())
+        } else if meta.path.is_ident("skip") {
+            if !self.field_level {
+                return Err(meta.error("`skip` can only be used on fields"));
             }
+            if meta.input.is_empty() || meta.input.peek(Token![,]) {
+                self.exclude = true;
+                return Ok(());
+            }
+            meta.parse_nested_meta(|meta| {
+                self.exclude |= MacroKind::from_path(&meta.path)? == self.kind;
+                Ok(())
+            })
+        } else if let Ok(kind) = MacroKind::from_path(&meta.path) {
+            if kind == self.kind {
+                meta.parse_nested_meta(|meta| self.options.parse_option(&meta, self.field_level))
+            } else {
+                // options of another derive are checked when that derive runs
+                let content;
+                syn::parenthesized!(content in meta.input);
+                content.parse::<TokenStream2>().map(drop)
+            }
+        } else {
+            Err(meta.error(
+                "unsupported smile attribute, expected `name`, `skip`, `getter`, `setter`, `wither` or `builder`",
+            ))
+        }
+    }
+
+    /// Deprecated `#[exclude]` and `#[exclude(setter, ...)]`, same as `#[smile(skip(...))]`
+    fn parse_exclude(&mut self, attr: &Attribute) -> syn::Result<()> {
+        // a bare `#[exclude]` applies to every derive
+        if let syn::Meta::Path(_) = attr.meta {
+            self.exclude = true;
+            return Ok(());
+        }
+        attr.parse_nested_meta(|meta| {
+            self.exclude |= MacroKind::from_path(&meta.path)? == self.kind;
+            Ok(())
+        })
+    }
+
+    /// Deprecated `#[name(cost)]`, same as `#[smile(name = "cost")]`
+    fn parse_name(&mut self, list: &syn::MetaList) -> syn::Result<()> {
+        if list.tokens.is_empty() {
+            return Err(syn::Error::new_spanned(list, "name is empty"));
+        }
+        let name = list.parse_args::<Ident>().map_err(|_| {
+            syn::Error::new_spanned(
+                &list.tokens,
+                "Only one identifier is required for the name on property",
+            )
+        })?;
+        self.name = Some(name);
+        Ok(())
+    }
+}
+
+/// Whether `attr` is one of the deprecated `#[exclude]`, `#[exclude(...)]` or `#[name(...)]`
+/// forms. Other shapes such as `#[name = "..."]` belong to other crates and are left alone
+fn is_deprecated_alias(attr: &Attribute) -> bool {
+    match attr.meta {
+        syn::Meta::Path(ref path) => path.is_ident("exclude"),
+        syn::Meta::List(ref list) => list.path.is_ident("exclude") || list.path.is_ident("name"),
+        syn::Meta::NameValue(_) => false,
+    }
+}
+
+/// Prefix and suffix around the field name forming a method name, configured through the
+/// `prefix = "..."` and `suffix = "..."` options of a derive
+#[derive(Clone, Default)]
+pub(crate) struct MethodName {
+    // `None` keeps the default prefix of the derive
+    prefix: Option<String>,
+    suffix: String,
+}
+
+impl MethodName {
+    /// Parses `prefix` or `suffix`, returning `false` if `meta` is another option
+    pub fn parse_option(&mut self, meta: &ParseNestedMeta) -> syn::Result<bool> {
+        if meta.path.is_ident("prefix") {
+            self.prefix = Some(Self::parse_affix(meta)?);
+        } else if meta.path.is_ident("suffix") {
+            self.suffix = Self::parse_affix(meta)?;
+        } else {
+            return Ok(false);
         }
-        false
+        Ok(true)
+    }
+
+    /// Reads a prefix or suffix, which must only contain characters allowed in identifiers
+    pub fn parse_affix(meta: &ParseNestedMeta) -> syn::Result<String> {
+        let lit: LitStr = meta.value()?.parse()?;
+        let value = lit.value();
+        // the affix must be able to continue an identifier, which `½` or `-` cannot
+        if !value.is_empty() && syn::parse_str::<Ident>(&format!("_{}", value)).is_err() {
+            return Err(syn::Error::new(
+                lit.span(),
+                "expected only characters allowed in identifiers",
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Method name for the field `name`, falling back to `default_prefix` when no prefix is set.
+    /// Names that are not valid identifiers, such as `0` without a prefix, start with `_`
+    pub fn ident(&self, default_prefix: &str, name: &str, span: Span) -> Ident {
+        let prefix = self.prefix.as_deref().unwrap_or(default_prefix);
+        self.ident_with_prefix(prefix, name, span)
     }
+
+    /// Method name for the field `name` with the given prefix instead of the configured one,
+    /// for the extra methods generated next to the main one
+    pub fn ident_with_prefix(&self, prefix: &str, name: &str, span: Span) -> Ident {
+        let method = if prefix.is_empty() && self.suffix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}{}{}", prefix, name.trim_start_matches("r#"), self.suffix)
+        };
+        // affixes are checked by `parse_affix` and field names are identifiers or positions, so
+        // `_` always makes them a valid identifier
+        let mut ident = syn::parse_str::<Ident>(&method)
+            .unwrap_or_else(|_| Ident::new(&format!("_{}", method), span));
+        ident.set_span(span);
+        ident
+    }
+}
+
+/// Visibility of a generated method, configured through the `vis = "..."` option of a derive
+#[derive(Clone, Default)]
+pub(crate) enum MethodVis {
+    // the visibility chosen by the derive, usually `pub`
+    #[default]
+    Default,
+    // `vis = "field"`, the visibility of the field the method is generated for
+    Field,
+    // `vis = "private"` or any visibility such as `vis = "pub(crate)"`
+    Explicit(Visibility),
+}
+
+impl MethodVis {
+    /// Parses `vis`, returning `false` if `meta` is another option
+    pub fn parse_option(&mut self, meta: &ParseNestedMeta) -> syn::Result<bool> {
+        if !meta.path.is_ident("vis") {
+            return Ok(false);
+        }
+        let lit: LitStr = meta.value()?.parse()?;
+        *self = match lit.value().as_str() {
+            "field" => MethodVis::Field,
+            "private" => MethodVis::Explicit(Visibility::Inherited),
+            _ => MethodVis::Explicit(lit.parse().map_err(|_| {
+                syn::Error::new(
+                    lit.span(),
+                    "expected `private`, `field` or a visibility such as `pub(crate)`",
+                )
+            })?),
+        };
+        Ok(true)
+    }
+
+    /// Visibility to emit, where `default` applies when no `vis` is set and `field` is the
+    /// visibility of the field (or of the type) the method is generated for
+    pub fn resolve(&self, default: TokenStream2, field: &Visibility) -> TokenStream2 {
+        match self {
+            MethodVis::Default => default,
+            MethodVis::Field => quote!(#field),
+            MethodVis::Explicit(vis) => quote!(#vis),
+        }
+    }
+}
+
+/// Reads a flag written as `flag` or `flag = true|false`
+pub(crate) fn parse_flag(meta: &ParseNestedMeta) -> syn::Result<bool> {
+    if meta.input.peek(Token![=]) {
+        Ok(meta.value()?.parse::<LitBool>()?.value)
+    } else {
+        Ok(true)
+    }
+}
+
+/// Whether `ty` is one of the primitive types that are always `Copy`, such as `i32` or `bool`
+pub(crate) fn is_copy_primitive(ty: &Type) -> bool {
+    const PRIMITIVES: [&str; 16] = [
+        "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+        "f32", "f64", "bool", "char",
+    ];
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .get_ident()
+            .is_some_and(|ident| PRIMITIVES.contains(&ident.to_string().as_str())),
+        Type::Tuple(tuple) => tuple.elems.is_empty(),
+        Type::Paren(paren) => is_copy_primitive(&paren.elem),
+        _ => false,
+    }
+}
+
+/// Type arguments of `ty` if it is a path type named `name`, such as `[T]` for `Vec<T>` or
+/// `std::vec::Vec<T>` with `name` set to `Vec`
+pub(crate) fn type_args<'a>(ty: &'a Type, name: &str) -> Option<Vec<&'a Type>> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+        Type::Paren(paren) => return type_args(&paren.elem, name),
+        _ => return None,
+    };
+    if segment.ident != name {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::None => Some(Vec::new()),
+        PathArguments::AngleBracketed(args) => Some(
+            args.args
+                .iter()
+                .filter_map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        PathArguments::Parenthesized(_) => None,
+    }
+}
+
+/// A std collection type, as recognised by the collection helpers
+pub(crate) enum Collection<'a> {
+    // `Vec<T>`, or `VecDeque<T>` when `deque` is set
+    List { item: &'a Type, deque: bool },
+    // `HashSet<T>` or `BTreeSet<T>`
+    Set { item: &'a Type },
+    // `HashMap<K, V>` or `BTreeMap<K, V>`
+    Map { key: &'a Type, value: &'a Type },
+}
+
+impl<'a> Collection<'a> {
+    /// Recognises `ty` by the name of its last path segment, `None` for other types
+    pub fn of(ty: &'a Type) -> Option<Self> {
+        let args = |names: [&str; 2]| names.iter().find_map(|name| type_args(ty, name));
+        if let Some([item, ..]) = type_args(ty, "Vec").as_deref() {
+            Some(Collection::List { item, deque: false })
+        } else if let Some([item, ..]) = type_args(ty, "VecDeque").as_deref() {
+            Some(Collection::List { item, deque: true })
+        } else if let Some([item, ..]) = args(["HashSet", "BTreeSet"]).as_deref() {
+            Some(Collection::Set { item })
+        } else if let Some([key, value, ..]) = args(["HashMap", "BTreeMap"]).as_deref() {
+            Some(Collection::Map { key, value })
+        } else {
+            None
+        }
+    }
+}
+
+/// Collects the results of every item, combining all errors instead of stopping at the first
+pub(crate) fn collect_results<T>(
+    results: impl IntoIterator<Item = syn::Result<T>>,
+) -> syn::Result<Vec<T>> {
+    let mut values = Vec::new();
+    let mut error: Option<syn::Error> = None;
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(err) => combine_error(&mut error, err),
+        }
+    }
+    match error {
+        Some(error) => Err(error),
+        None => Ok(values),
+    }
+}
+
+/// Adds `err` to the errors collected so far
+pub(crate) fn combine_error(error: &mut Option<syn::Error>, err: syn::Error) {
+    match error {
+        Some(ref mut error) => error.combine(err),
+        None => *error = Some(err),
+    }
+}
+
+/// Deprecated attributes already warned about. Every derive on the struct sees the same
+/// attributes, so only the first one to expand emits the warning
+static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Warnings for the deprecated bare `#[exclude]` and `#[name(...)]` field attributes. rustc has
+/// no warning API for derives, so each attribute becomes a use of a `#[deprecated]` constant
+/// spanned on it, carrying the lint attributes of the struct and the field
+pub(crate) fn deprecated_attrs(input: &DeriveInput) -> TokenStream2 {
+    let fields: Vec<&Field> = match input.data {
+        syn::Data::Struct(ref data) => data.fields.iter().collect(),
+        syn::Data::Enum(ref data) => data.variants.iter().flat_map(|v| &v.fields).collect(),
+        syn::Data::Union(_) => Vec::new(),
+    };
+    let lints = |attrs: &[Attribute]| -> Vec<Attribute> {
+        let levels = ["allow", "expect", "warn", "deny", "forbid"];
+        attrs
+            .iter()
+            .filter(|attr| levels.iter().any(|level| attr.path().is_ident(level)))
+            .cloned()
+            .collect()
+    };
+    let struct_lints = lints(&input.attrs);
+    let mut warned = WARNED.lock().unwrap_or_else(|err| err.into_inner());
+    let warnings = fields.iter().flat_map(|field| {
+        let field_lints = lints(&field.attrs);
+        let struct_lints = &struct_lints;
+        field
+            .attrs
+            .iter()
+            .filter(|attr| is_deprecated_alias(attr))
+            .filter(|attr| warned.insert(format!("{:?}", attr.path().span())))
+            .filter_map(move |attr| {
+                let note = if attr.path().is_ident("exclude") {
+                    "use `#[smile(skip)]` or `#[smile(skip(...))]` instead of `#[exclude]`"
+                } else {
+                    "use `#[smile(name = \"...\")]` instead of `#[name(...)]`"
+                };
+                // named after the attribute so that rustc reports a use of deprecated `exclude`
+                let used = attr.path().get_ident()?;
+                let item = Ident::new(&used.to_string(), Span::call_site());
+                Some(quote! {
+                    #(#struct_lints)*
+                    #(#field_lints)*
+                    const _: () = {
+                        #[deprecated(note = #note)]
+                        #[allow(non_upper_case_globals)]
+                        const #item: () = ();
+                        let _ = #used;
+                    };
+                })
+            })
+            .collect::<Vec<_>>()
+    });
+    quote!(#(#warnings)*)
 }
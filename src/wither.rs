@@ -1,29 +1,108 @@
-use crate::util::StructContext;
-use crate::TokenStream1;
-use quote::{format_ident, quote};
+use crate::util::{
+    deprecated_attrs, parse_flag, DeriveOptions, MacroKind, MethodName, MethodVis, StructContext,
+};
+use crate::{TokenStream1, TokenStream2};
+use quote::quote;
+use syn::meta::ParseNestedMeta;
 use syn::spanned::Spanned;
 use syn::DeriveInput;
 
+/// Options declared through `#[wither(...)]`, fields inherit the ones set on the struct
+#[derive(Default, Clone)]
+struct WitherOptions {
+    method: MethodName,
+    vis: MethodVis,
+    // `with_x` takes the new value and the closure form becomes `map_x`
+    value: bool,
+    // prefix of the closure form when `value` is set, `map_` by default
+    map_prefix: Option<String>,
+    // take `&self` and return a modified clone instead of consuming `self`
+    clone: bool,
+}
+
+impl DeriveOptions for WitherOptions {
+    fn parse_option(&mut self, meta: &ParseNestedMeta, _: bool) -> syn::Result<()> {
+        if self.method.parse_option(meta)? || self.vis.parse_option(meta)? {
+            Ok(())
+        } else if meta.path.is_ident("value") {
+            self.value = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("clone") {
+            self.clone = parse_flag(meta)?;
+            Ok(())
+        } else if meta.path.is_ident("map_prefix") {
+            self.map_prefix = Some(MethodName::parse_affix(meta)?);
+            Ok(())
+        } else {
+            Err(meta.error(
+                "unsupported wither attribute, expected `prefix`, `suffix`, `vis`, `value`, `map_prefix` or `clone`",
+            ))
+        }
+    }
+}
+
 pub(crate) fn gen_ast(input: &DeriveInput) -> TokenStream1 {
-    let context = StructContext::new(input);
+    let mut ast = expand(input).unwrap_or_else(syn::Error::into_compile_error);
+    ast.extend(deprecated_attrs(input));
+    ast.into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let context = StructContext::new(input)?;
     let impl_sign = context.impl_sign();
-    let fields = context.handle_fields_exclude_and_name_attr();
+    let fields = context.handle_fields_exclude_and_name_attr::<WitherOptions>(MacroKind::Wither)?;
 
     let gen_fns = fields
         .iter()
         .map(|fc| {
-            let mut fn_name = format_ident!("with_{}", fc.name);
-            fn_name.set_span(fc.ident.span());
+            let fn_name = fc.options.method.ident("with_", &fc.name, fc.member.span());
+            let vis = fc.options.vis.resolve(quote!(pub), fc.vis);
 
             let ty = fc.ty;
-            let ident = fc.ident;
+            let ident = &fc.member;
+            // clone withers modify a copy of `self`, which must be `Clone`
+            let (receiver, this, bound) = if fc.options.clone {
+                (
+                    quote!(&self),
+                    quote!(let mut this = ::core::clone::Clone::clone(self);),
+                    quote!(Self: ::core::clone::Clone,),
+                )
+            } else {
+                (quote!(self), quote!(let mut this = self;), quote!())
+            };
+            if !fc.options.value {
+                return quote! {
+                    #[inline]
+                    #vis fn #fn_name<F> (#receiver,func: F) -> Self
+                        where F: FnOnce(#ty) -> #ty, #bound
+                    {
+                        #this
+                        this.#ident = func(this.#ident);
+                        this
+                    }
+                };
+            }
+            let map_prefix = fc.options.map_prefix.as_deref().unwrap_or("map_");
+            let map_name =
+                fc.options
+                    .method
+                    .ident_with_prefix(map_prefix, &fc.name, fc.member.span());
             quote! {
-                 #[inline]
-                pub fn #fn_name<F> (mut self,func: F) -> Self
-                    where F: FnOnce(#ty) -> #ty
+                #[inline]
+                #vis fn #fn_name (#receiver, value: #ty) -> Self
+                    where #bound
                 {
-                    self.#ident = func(self.#ident);
-                    self
+                    #this
+                    this.#ident = value;
+                    this
+                }
+                #[inline]
+                #vis fn #map_name<F> (#receiver,func: F) -> Self
+                    where F: FnOnce(#ty) -> #ty, #bound
+                {
+                    #this
+                    this.#ident = func(this.#ident);
+                    this
                 }
             }
         })
@@ -34,5 +113,5 @@ pub(crate) fn gen_ast(input: &DeriveInput) -> TokenStream1 {
             #(#gen_fns)*
         }
     };
-    ast.into()
+    Ok(ast)
 }